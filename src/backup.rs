@@ -0,0 +1,207 @@
+use std::{collections::HashMap, fs::File, io::{Read, Write}, path::{Path, PathBuf}};
+
+use chrono::Datelike;
+use eframe::APP_KEY;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{HanoiApp, APP_NAME};
+
+const COMPRESSED_EXTENSION: &str = "ron.gz";
+
+/// When and how the save file gets backed up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    /// Back up the save once per day when launching, instead of only with `--backup`.
+    pub auto: bool,
+    /// Write the backups gzip compressed.
+    pub compress: bool,
+    /// How many backups to keep, the oldest ones get deleted. Zero keeps all of them.
+    pub retention: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            compress: false,
+            retention: 14,
+        }
+    }
+}
+
+fn main_file(dir: &Path) -> PathBuf {
+    dir.join(format!("{APP_KEY}.ron"))
+}
+
+/// The name every backup made on `date` starts with.
+fn backup_prefix(date: impl Datelike) -> String {
+    format!("{APP_KEY} BACKUP {}_{}_{}", date.year(), date.month(), date.day())
+}
+
+fn is_backup(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(&format!("{APP_KEY} BACKUP ")) && (name.ends_with(".ron") || name.ends_with(COMPRESSED_EXTENSION)))
+}
+
+/// Every backup in `dir`, from the oldest to the newest.
+fn backup_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return vec![] };
+    let mut backups = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_backup(path))
+        .filter_map(|path| Some((path.metadata().and_then(|metadata| metadata.modified()).ok()?, path)))
+        .collect::<Vec<_>>();
+    backups.sort();
+    backups.into_iter().map(|(_, path)| path).collect()
+}
+
+fn backed_up_today(dir: &Path) -> bool {
+    let prefix = backup_prefix(chrono::Utc::now());
+    backup_files(dir).iter().any(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(&prefix))
+            // 2025_1_1 shouldn't match 2025_1_15
+            .is_some_and(|rest| rest.starts_with([' ', '.']))
+    })
+}
+
+/// Backs up the save when the settings ask for it, or anyway when `forced`.
+pub fn auto_backup(settings: BackupSettings, forced: bool) {
+    let Some(dir) = eframe::storage_dir(APP_NAME) else { return };
+    if forced || (settings.auto && !backed_up_today(&dir)) {
+        if let Err(err) = backup_save(&dir, settings) {
+            eprintln!("Unable to back up the save: {err}");
+        }
+    }
+}
+
+/// Copies the save next to it with the date in its name, then deletes the backups past the retention count.
+pub fn backup_save(dir: &Path, settings: BackupSettings) -> std::io::Result<PathBuf> {
+    let main_file = main_file(dir);
+    // let's assume that this process does not take a full day to complete
+    let prefix = backup_prefix(chrono::Utc::now());
+    let extension = if settings.compress { COMPRESSED_EXTENSION } else { "ron" };
+
+    for i in 0..1000 {
+        let postfix = if i == 0 { "".to_string() } else { format!(" {}", i) };
+        let output_file = dir.join(format!("{prefix}{postfix}.{extension}"));
+        if output_file.exists() { continue }
+
+        if settings.compress {
+            let save = std::fs::read(&main_file)?;
+            let mut encoder = GzEncoder::new(File::create_new(&output_file)?, Compression::default());
+            encoder.write_all(&save)?;
+            encoder.finish()?;
+        } else {
+            std::fs::copy(&main_file, &output_file)?;
+        }
+        prune_backups(dir, settings.retention);
+        return Ok(output_file)
+    }
+
+    Err(std::io::Error::other("too many backups today"))
+}
+
+fn prune_backups(dir: &Path, retention: usize) {
+    if retention == 0 { return }
+    let backups = backup_files(dir);
+    for backup in &backups[..backups.len().saturating_sub(retention)] {
+        let _ = std::fs::remove_file(backup);
+    }
+}
+
+/// The save stored in a backup, compressed or not.
+pub fn read_backup(path: &Path) -> std::io::Result<String> {
+    let mut save = String::new();
+    if path.to_string_lossy().ends_with(COMPRESSED_EXTENSION) {
+        GzDecoder::new(File::open(path)?).read_to_string(&mut save)?;
+    } else {
+        File::open(path)?.read_to_string(&mut save)?;
+    }
+    Ok(save)
+}
+
+/// A save file read back as a storage, in the layout of eframe's file storage: a ron map from keys to ron values.
+struct SaveFile(HashMap<String, String>);
+
+impl eframe::Storage for SaveFile {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+    fn set_string(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+    fn flush(&mut self) {}
+}
+
+/// Replaces the save with a backup, after backing up the current one in case it was the wrong file.
+pub fn restore_backup(path: &Path) -> Result<(), String> {
+    let dir = eframe::storage_dir(APP_NAME).ok_or("No save directory")?;
+    let save = read_backup(path).map_err(|err| format!("Unable to read the backup: {err}"))?;
+    let storage = SaveFile(ron::from_str(&save).map_err(|err| format!("Not a save file: {err}"))?);
+    eframe::get_value::<HanoiApp>(&storage, APP_KEY).ok_or("The backup holds no readable save")?;
+
+    if main_file(&dir).exists() {
+        backup_save(&dir, BackupSettings { retention: 0, ..Default::default() })
+            .map_err(|err| format!("Unable to back up the current save: {err}"))?;
+    }
+    std::fs::write(main_file(&dir), save).map_err(|err| format!("Unable to write the save: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use eframe::APP_KEY;
+
+    use crate::HanoiApp;
+
+    use super::{backup_files, backup_save, backed_up_today, main_file, read_backup, BackupSettings, SaveFile};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hanoi-speedrapp-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn compressed_backups_read_back_and_get_pruned() {
+        let dir = temp_dir("backups");
+        let save = format!("{{\"{APP_KEY}\": \"()\"}}");
+        std::fs::write(main_file(&dir), &save).unwrap();
+        assert!(!backed_up_today(&dir));
+
+        let settings = BackupSettings { compress: true, retention: 2, ..Default::default() };
+        let first = backup_save(&dir, settings).unwrap();
+        assert!(backed_up_today(&dir));
+        assert_eq!(read_backup(&first).unwrap(), save);
+        assert_ne!(std::fs::read(&first).unwrap(), save.as_bytes(), "the backup is compressed");
+
+        let plain = backup_save(&dir, BackupSettings { compress: false, ..settings }).unwrap();
+        assert_eq!(read_backup(&plain).unwrap(), save);
+        backup_save(&dir, settings).unwrap();
+        let backups = backup_files(&dir);
+        assert_eq!(backups.len(), 2);
+        assert!(!backups.contains(&first), "the oldest backup is deleted");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saves_read_back_through_the_storage() {
+        let app = HanoiApp { scramble_seed: 7, ..Default::default() };
+        let mut storage = SaveFile(Default::default());
+        eframe::set_value(&mut storage, APP_KEY, &app);
+        let file = ron::to_string(&storage.0).unwrap();
+
+        let storage = SaveFile(ron::from_str(&file).unwrap());
+        assert_eq!(eframe::get_value::<HanoiApp>(&storage, APP_KEY).map(|app| app.scramble_seed), Some(7));
+        assert!(eframe::get_value::<HanoiApp>(&SaveFile(Default::default()), APP_KEY).is_none());
+    }
+}
@@ -1,3 +1,7 @@
+#[cfg(feature = "spectator")]
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
 use clap::Parser;
 
 /// Hanoi-Speedrapp
@@ -7,11 +11,29 @@ pub struct Cli {
     #[arg(long, short)]
     pub profile: bool,
 
-    /// Backup savefile
+    /// Backup savefile, even if it was already backed up today
     #[arg(long, short)]
     pub backup: bool,
 
+    /// Replace the savefile with a backup, compressed or not
+    #[arg(long)]
+    pub restore: Option<PathBuf>,
+
     /// Enable VSync
     #[arg(long, short)]
     pub vsync: bool,
+
+    /// Load the quick keys from a RON or JSON file
+    #[arg(long, short)]
+    pub quick_keys: Option<PathBuf>,
+
+    /// Share the live game with spectators connecting to this address
+    #[cfg(feature = "spectator")]
+    #[arg(long)]
+    pub host_spectators: Option<SocketAddr>,
+
+    /// Watch the live game shared at this address
+    #[cfg(feature = "spectator")]
+    #[arg(long, conflicts_with = "host_spectators")]
+    pub spectate: Option<SocketAddr>,
 }
@@ -0,0 +1,171 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use eframe::egui::ahash::AHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{hanoi::HanoiGame, highscores::Score, HanoiApp};
+
+pub const DAILY_DISKS: usize = 7;
+pub const DAILY_POLES: usize = 3;
+
+/// What the runs of a daily challenge are kept under, apart from the normal high scores.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DailyHeader {
+    pub date: NaiveDate,
+    pub disks: usize,
+    pub poles: usize,
+}
+
+pub type DailyHighscores = AHashMap<DailyHeader, Vec<Score>>;
+
+impl DailyHeader {
+    pub fn of(date: NaiveDate) -> Self {
+        Self {
+            date,
+            disks: DAILY_DISKS,
+            poles: DAILY_POLES,
+        }
+    }
+
+    pub fn today() -> Self {
+        Self::of(Utc::now().date_naive())
+    }
+
+    /// The same for everyone on the same UTC day, it reads like the date.
+    pub fn seed(&self) -> u64 {
+        self.date.year() as u64 * 10000 + self.date.month() as u64 * 100 + self.date.day() as u64
+    }
+}
+
+/// The settings from before the daily challenge, that it overrides until it's left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BeforeDaily {
+    hanoi: HanoiGame,
+    scramble: bool,
+    scramble_seed: u64,
+    blindfold: bool,
+    outline: bool,
+}
+
+impl BeforeDaily {
+    fn of(app: &HanoiApp) -> Self {
+        Self {
+            hanoi: app.hanoi.restarted(),
+            scramble: app.scramble,
+            scramble_seed: app.scramble_seed,
+            blindfold: app.blindfold,
+            outline: app.outline,
+        }
+    }
+
+    pub fn restore(&self, app: &mut HanoiApp) {
+        app.hanoi = self.hanoi.clone();
+        app.scramble = self.scramble;
+        app.scramble_seed = self.scramble_seed;
+        app.blindfold = self.blindfold;
+        app.outline = self.outline;
+    }
+}
+
+impl HanoiApp {
+    pub fn start_daily(&mut self) {
+        if self.before_daily.is_none() {
+            self.before_daily = Some(BeforeDaily::of(self));
+        }
+        self.daily = Some(DailyHeader::today());
+        self.soft_reset();
+    }
+
+    pub fn leave_daily(&mut self) {
+        self.end_daily();
+        self.soft_reset();
+    }
+
+    /// Stops the daily challenge and puts back the settings it replaced, without resetting.
+    pub fn end_daily(&mut self) {
+        self.daily = None;
+        if let Some(before) = self.before_daily.take() {
+            before.restore(self);
+        }
+    }
+
+    /// Puts back the settings of the daily challenge being played, on every reset so that they can't be changed until it's left.
+    pub fn apply_daily(&mut self) {
+        let Some(daily) = self.daily else { return };
+        self.hanoi.disks_count = daily.disks;
+        self.hanoi.poles_count = daily.poles;
+        self.hanoi.start_pole = 1;
+        self.hanoi.end_pole = None;
+        self.hanoi.illegal_moves = false;
+        self.hanoi.spread_goal = false;
+        self.hanoi.merge_start = false;
+        self.hanoi.multi_move = false;
+        self.blindfold = false;
+        self.outline = false;
+        self.scramble = true;
+        self.scramble_seed = daily.seed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{GameState, HanoiApp};
+
+    use super::{DailyHeader, DAILY_DISKS, DAILY_POLES};
+
+    #[test]
+    fn daily_challenges_are_kept_apart() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let daily = DailyHeader::of(date);
+        assert_eq!(daily.seed(), 20250115);
+
+        let mut app = HanoiApp::default();
+        app.start_daily();
+        app.daily = Some(daily);
+        app.soft_reset();
+        let board = app.hanoi.board();
+        app.hanoi.disks_count = 3;
+        app.soft_reset();
+        assert_eq!(app.hanoi.disks_count, DAILY_DISKS, "the size is locked for the day");
+        assert_eq!(app.hanoi.board(), board, "everyone gets the same board");
+
+        for (from, to) in app.hanoi.solve().unwrap() {
+            app.full_move(from, to, None);
+        }
+        app.check_finished(&Default::default());
+        assert!(matches!(app.state, GameState::Finished(_)));
+        assert_eq!(app.daily_highscores[&daily].len(), 1);
+        assert!(app.highscores.is_empty(), "daily runs don't mix with the normal ones");
+
+        app.leave_daily();
+        assert_eq!(app.hanoi.poles[0].len(), HanoiApp::default().hanoi.disks_count, "back to the tower from before the challenge");
+    }
+
+    #[test]
+    fn leaving_the_daily_restores_the_settings() {
+        let mut app = HanoiApp {
+            scramble: true,
+            scramble_seed: 42,
+            fixed_seed: true,
+            blindfold: true,
+            ..Default::default()
+        };
+        app.hanoi.poles_count = 4;
+        app.hanoi.disks_count = 9;
+        app.soft_reset();
+        let board = app.hanoi.board();
+
+        app.start_daily();
+        assert_eq!((app.hanoi.poles_count, app.hanoi.disks_count, app.blindfold), (DAILY_POLES, DAILY_DISKS, false));
+        assert_eq!(app.saved().scramble_seed, 42, "the daily's seed isn't saved");
+        app.blindfold = true;
+        app.soft_reset();
+        assert!(!app.blindfold, "blindfold is locked for the day");
+
+        app.leave_daily();
+        assert_eq!((app.hanoi.poles_count, app.hanoi.disks_count), (4, 9));
+        assert_eq!((app.scramble, app.scramble_seed, app.blindfold), (true, 42, true));
+        assert_eq!(app.hanoi.board(), board);
+    }
+}
@@ -1,28 +1,76 @@
-use std::{fmt::Debug, sync::Arc, time::{Duration, Instant}};
+use std::{fmt::Debug, ops::RangeInclusive, sync::Arc, time::{Duration, Instant}};
 
-use eframe::{egui::{self, mutex::Mutex, panel::Side, vec2, Align, Align2, Area, CentralPanel, Color32, ComboBox, Direction, DragValue, Event, FontId, Id, Key, LayerId, Layout, Order, Painter, Pos2, Response, RichText, Sense, SidePanel, Slider, Stroke, TextStyle, TopBottomPanel, Ui, Vec2, Window}, emath::Numeric};
+use eframe::{egui::{self, mutex::Mutex, panel::Side, vec2, Align, Align2, Area, Button, CentralPanel, Checkbox, Color32, ComboBox, Direction, DragValue, Event, FontId, Id, Key, LayerId, Layout, Modal, Order, Painter, PointerButton, Pos2, ProgressBar, Rect, Response, RichText, Sense, Shape, SidePanel, Slider, Stroke, TextStyle, TopBottomPanel, Ui, UiBuilder, Vec2, ViewportCommand, Window}, emath::Numeric};
 use egui_dnd::Dnd;
 use egui_extras::{Column, TableBuilder};
-use egui_plot::{Bar, BarChart};
+use egui_plot::{Bar, BarChart, HLine, Legend, LineStyle, Points};
 use indoc::formatdoc;
 use once_cell::sync::Lazy;
 use pretty_duration::pretty_duration;
 use serde::{Deserialize, Serialize};
 use strum::{EnumIter, IntoEnumIterator};
-use themes::draw_share_tower;
+use frames::FRAME_EXPORT_JOB;
+use themes::{draw_share_tower, draw_theme_preview, parse_hex_palette, ColorTheme};
 
-use crate::{get_cursor_position, hanoi::{RequiredMoves, MAX_DISKS, MAX_DISKS_NORMAL, MAX_POLES, MAX_POLES_NORMAL}, play::{PlayerKind, HUMAN_PLAY}, GameState, HanoiApp, PolesVec, APP_NAME};
+use crate::{util::ResetMode, consistency_score, pole_column, get_cursor_position, input_breakdown, is_perfectly_consistent, move_efficiency, daily::DailyHeader, highscores::Move, highscores::{Header, HeaderRuns, SharedReplay}, DEFAULT_MOVE_HISTORY_LIMIT, hanoi::{HanoiGame, RequiredMoves, MAX_DISKS, MAX_DISKS_NORMAL, MAX_POLES, MAX_POLES_NORMAL}, play::{connected_gamepads, load_quick_keys, out_of_range_quick_keys, save_quick_keys, GamepadAction, GamepadInput, GamepadSettings, InputSource, PlayerKind, QuickInput, QuickKey, HUMAN_PLAY, HUMAN_PLAY_COUNT, SWIFT_KEYS}, sound::{audio_available, DEFAULT_METRONOME_BPM, MAX_METRONOME_BPM, MIN_METRONOME_BPM}, GameState, HanoiApp, PolesVec, APP_NAME};
 
+pub mod diagnostics;
+pub mod frames;
 pub mod themes;
 
 const DISK_HEIGHT: f32 = 30.0;
+const COMPACT_DISK_HEIGHT: f32 = 8.0;
+const MIN_DISK_HEIGHT: f32 = 2.0;
+pub const MIN_WINDOW_SIZE: Vec2 = Vec2::new(480.0, 320.0);
 const DISK_WIDTH_MIN: f32 = 20.0;
+const DISK_WIDTH_STEP: f32 = 12.0;
+const INFOS_PANEL_WIDTH: f32 = 600.0;
+const TOP_BAR_HEIGHT: f32 = 60.0;
+const MAX_INITIAL_WINDOW_SIZE: Vec2 = Vec2::new(1600.0, 1000.0);
 const POLE_WIDTH: f32 = 3.0;
 const POLE_COLOR: Color32 = Color32::WHITE;
 const TEXT_COLOR: Color32 = Color32::WHITE;
 const TEXT_OUTLINE_COLOR: Color32 = Color32::BLACK;
 const SHARE_BUTTON_DURATION: Duration = Duration::from_millis(1000);
-const DEFAULT_QUICK_KEY: (Key, usize, usize) = (Key::Space, 1, 2);
+static LAST_SHARE: Lazy<Arc<Mutex<Instant>>> = Lazy::new(|| Arc::new(Mutex::new(Instant::now() - SHARE_BUTTON_DURATION)));
+const HIGHSCORE_COLOR: Color32 = Color32::from_rgb(0xFF, 0xA5, 0x00);
+const AFTERIMAGE_DURATION: Duration = Duration::from_millis(300);
+const AFTERIMAGE_OPACITY: f32 = 0.4;
+const TARGET_PREVIEW_OPACITY: f32 = 0.15;
+const OUTLINE_WIDTH: f32 = 2.0;
+const THEME_PREVIEW_HEIGHT: f32 = 16.0;
+const REPLAY_EXTENSION: &str = "hanoi";
+const GHOST_OPACITY: f32 = 0.25;
+const COMPLETED_DIM_ALPHA: u8 = 160;
+const MAX_ANIMATION_MILLIS: u64 = 500;
+const HINT_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+const HINT_WIDTH: f32 = 4.0;
+const HINT_MARGIN: f32 = 24.0;
+const CYCLE_CURSOR_WIDTH: f32 = 2.0;
+const DISK_NUMBER_OUTLINE_REFERENCE: f32 = 20.0;
+const MIN_GRAPH_HEIGHT: u32 = 64;
+const MAX_GRAPH_HEIGHT: u32 = 1024;
+const MIN_FRAME_SIZE: u32 = 64;
+const MAX_FRAME_SIZE: u32 = 7680;
+const CELEBRATION_DURATION: Duration = Duration::from_millis(1500);
+const CELEBRATION_CONFETTI: usize = 64;
+const CONFETTI_SIZE: f32 = 8.0;
+const TOAST_DURATION: Duration = Duration::from_millis(1500);
+const TOAST_MARGIN: f32 = 32.0;
+const DEFAULT_QUICK_KEY: QuickKey = (QuickInput::Key(Key::Space), 1, 2);
+const MIN_MOVE_HISTORY_LIMIT: usize = 1000;
+const HUD_MARGIN: f32 = 8.0;
+const GOAL_BAR_WIDTH: f32 = 160.0;
+const DEFAULT_GOAL_TIME: Duration = Duration::from_secs(10);
+
+const ILLEGAL_COUNTING_HELP: &str = "How moves are counted with illegal moves enabled:
+Every: every move counts as one
+LegalOnly: placing a larger disk on a smaller one is free
+Penalty: placing a larger disk on a smaller one counts as two";
+
+const MULTI_MOVE_COUNTING_HELP: &str = "How moves of several disks are counted with multi-moves enabled:
+Single: moving a group counts as one
+PerDisk: moving a group counts as one per disk in it";
 
 const TIME_ESTIMATIONS: &[(&str, f64)] = &[
     ("an expert physical player", 3.0),
@@ -32,7 +80,7 @@ const TIME_ESTIMATIONS: &[(&str, f64)] = &[
     ("a computer", 50000000.0),
 ];
 
-static DEFAULT_HANOI_APP: Lazy<HanoiApp> = Lazy::new(|| {
+pub static DEFAULT_HANOI_APP: Lazy<HanoiApp> = Lazy::new(|| {
     let mut hanoi_app = HanoiApp::default();
     hanoi_app.soft_reset();
     hanoi_app
@@ -45,6 +93,134 @@ pub enum PolesPosition {
     Top,
 }
 
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum DiskShape {
+    #[default]
+    Rounded,
+    Rectangle,
+    /// Narrower on the side facing away from the base of the pole, like a classic wooden tower.
+    Trapezoid,
+}
+
+/// The window's outer position and inner size, in points.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum ShareTimeFormat {
+    #[default]
+    Seconds,
+    Minutes,
+}
+
+impl ShareTimeFormat {
+    pub fn format(self, time: Duration) -> String {
+        // rounded to whole milliseconds first, so that the seconds never round up to 60
+        let millis = (time.as_micros() + 500) / 1000;
+        match self {
+            ShareTimeFormat::Seconds => format!("{:.3} seconds", millis as f64 / 1000.0),
+            ShareTimeFormat::Minutes => format!("{}:{:06.3}", millis / 60000, (millis % 60000) as f64 / 1000.0),
+        }
+    }
+}
+
+/// Which lines are shown in the completed window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompletedStats {
+    pub optimal: bool,
+    pub moves_per_second: bool,
+    pub optimal_moves_per_second: bool,
+    pub consistency: bool,
+    pub inputs: bool,
+    pub efficiency: bool,
+    pub rank: bool,
+    pub best_time: bool,
+}
+
+impl Default for CompletedStats {
+    fn default() -> Self {
+        Self {
+            optimal: true,
+            moves_per_second: true,
+            optimal_moves_per_second: true,
+            consistency: false,
+            inputs: false,
+            efficiency: false,
+            rank: false,
+            best_time: true,
+        }
+    }
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum HudCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl HudCorner {
+    fn align(self) -> Align2 {
+        match self {
+            HudCorner::TopLeft => Align2::LEFT_TOP,
+            HudCorner::TopRight => Align2::RIGHT_TOP,
+            HudCorner::BottomLeft => Align2::LEFT_BOTTOM,
+            HudCorner::BottomRight => Align2::RIGHT_BOTTOM,
+        }
+    }
+}
+
+/// Compact timer, moves and pace overlay, meant for capturing the game while streaming.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudSettings {
+    pub enabled: bool,
+    pub corner: HudCorner,
+    pub font_size: u8,
+    pub background_alpha: u8,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corner: Default::default(),
+            font_size: 16,
+            background_alpha: 160,
+        }
+    }
+}
+
+/// How the numbers are written on the disks.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskNumberSettings {
+    /// The smallest font size numbers are written with, in points.
+    pub min_size: u8,
+    /// Leave out the numbers of disks too thin for `min_size`, instead of overflowing them.
+    pub hide_when_small: bool,
+    /// Thickness of the outline at a font size of `DISK_NUMBER_OUTLINE_REFERENCE`.
+    pub outline: u8,
+}
+
+impl Default for DiskNumberSettings {
+    fn default() -> Self {
+        Self {
+            min_size: 8,
+            hide_when_small: false,
+            outline: 1,
+        }
+    }
+}
+
 macro_rules! check_changed {
     ($action:expr; $($resp:expr;)*) => {
         if [$(
@@ -76,16 +252,57 @@ impl HanoiApp {
 
                 ui.vertical(|ui| {
                     self.draw_state(ui);
+                    #[cfg(feature = "spectator")]
+                    self.draw_spectator_status(ui);
                 });
 
+                if matches!(self.player, PlayerKind::Replay(..)) && matches!(self.state, GameState::Playing(_)) {
+                    let pause_text = if self.replay_paused() { "Resume" } else { "Pause" };
+                    if ui.button(format!("{pause_text} ({:?})", self.pause_key)).clicked() {
+                        self.toggle_replay_pause();
+                    }
+                }
+
+                if let PlayerKind::Replay(ref score, index) = self.player {
+                    let mut target = index;
+                    let seek = ui.add(Slider::new(&mut target, 0..=score.moves.len()).text("Move"))
+                        .on_hover_text("Jump to any move of the replay, it goes on from there");
+                    if seek.changed() {
+                        self.seek_replay(target);
+                    }
+                }
+
+                if matches!(self.state, GameState::Finished(_)) && !self.completed_window && ui.button("Last result").clicked() {
+                    self.completed_window = true;
+                }
+
                 ui.separator();
                 
                 if ui.button(format!("Undo ({:?})", self.undo_key)).clicked() && matches!((&self.player, &self.state), (PlayerKind::Human, GameState::Playing(_))) {
                     self.undo_move();
                 }
 
+                if ui.add_enabled(self.redo_count() > 0, Button::new(format!("Redo ({:?})", self.redo_key))).clicked() && matches!((&self.player, &self.state), (PlayerKind::Human, GameState::Playing(_))) {
+                    self.redo_move();
+                }
+
+                if self.player == PlayerKind::Human {
+                    let (undo_count, redo_count) = (self.undo_count(), self.redo_count());
+                    ui.label(format!("↶ {undo_count} | {redo_count} ↷"))
+                        .on_hover_text(format!("{undo_count} moves can be undone, {redo_count} can be redone"));
+                }
+
                 if ui.button(format!("Reset ({:?})", self.reset_key)).clicked() {
-                    self.soft_reset();
+                    self.retry();
+                }
+
+                if ui.add_enabled(self.last_header.is_some(), Button::new(format!("Rematch ({:?})", self.rematch_key))).clicked() {
+                    self.rematch();
+                }
+
+                let bot_text = if self.player == PlayerKind::Bot { "Stop bot" } else { "Bot" };
+                if ui.button(format!("{bot_text} ({:?})", self.bot_key)).on_hover_text("Watch the bot solve the current settings").clicked() {
+                    self.toggle_bot();
                 }
 
                 if ui.button("Settings").clicked() {
@@ -107,6 +324,40 @@ impl HanoiApp {
         });
     }
 
+    /// Keeps the window's position and size up to date, to be saved with the app.
+    pub fn track_window(&mut self, ctx: &egui::Context) {
+        let viewport = ctx.input(|i| i.viewport().clone());
+        if viewport.minimized == Some(true) { return }
+        if let (Some(outer), Some(inner)) = (viewport.outer_rect, viewport.inner_rect) {
+            self.window = Some(WindowGeometry {
+                x: outer.min.x.round() as i32,
+                y: outer.min.y.round() as i32,
+                width: inner.width().round() as u32,
+                height: inner.height().round() as u32,
+            });
+        }
+    }
+
+    /// Puts the window back where it was saved, or sizes it for the game the first time.
+    pub fn restore_window(&self, ctx: &egui::Context) {
+        match self.window {
+            Some(window) => {
+                ctx.send_viewport_cmd(ViewportCommand::InnerSize(vec2(window.width as f32, window.height as f32).max(MIN_WINDOW_SIZE)));
+                ctx.send_viewport_cmd(ViewportCommand::OuterPosition(Pos2::new(window.x as f32, window.y as f32)));
+            },
+            None => ctx.send_viewport_cmd(ViewportCommand::InnerSize(self.default_window_size())),
+        }
+    }
+
+    pub fn default_window_size(&self) -> Vec2 {
+        let disks = self.hanoi.disks_count as f32;
+        let poles = self.hanoi.poles_count as f32;
+        let infos_panel = if self.infos_panel { INFOS_PANEL_WIDTH } else { 0.0 };
+        let width = poles * (DISK_WIDTH_MIN + disks * DISK_WIDTH_STEP) + infos_panel;
+        let height = TOP_BAR_HEIGHT + (disks + 2.0) * DISK_HEIGHT;
+        vec2(width, height).clamp(MIN_WINDOW_SIZE, MAX_INITIAL_WINDOW_SIZE)
+    }
+
     pub fn draw_central_panel(&mut self, ctx: &egui::Context) {
         puffin::profile_function!();
 
@@ -117,11 +368,22 @@ impl HanoiApp {
             if self.blindfold && matches!((&self.player, &self.state), (PlayerKind::Human, GameState::Playing(_))) {
                 self.draw_blindfold(ui);
             } else {
+                let draw_start = Instant::now();
                 let poles = self.draw_poles(ui, pointer_pos);
-                (*HUMAN_PLAY).lock().iter_mut().for_each(|p| p.poles_play(self, &poles, pointer_pos));
+                self.diagnostics.record_draw(draw_start.elapsed());
+                self.check_draw_budget();
+                for play in (*HUMAN_PLAY).lock().iter_mut() {
+                    if self.is_human_play_enabled(play.source()) {
+                        play.poles_play(self, &poles, pointer_pos);
+                    }
+                }
                 self.draw_dragging_disk(ui);
                 self.draw_swift_disk(ui);
             }
+            self.draw_celebration(ui);
+            if self.dim_completed && self.completed_window && matches!(self.state, GameState::Finished(_)) {
+                ui.painter().rect_filled(ui.clip_rect(), 0.0, Color32::from_black_alpha(COMPLETED_DIM_ALPHA));
+            }
             self.draw_windows(ui.ctx());
         });
     }
@@ -132,10 +394,110 @@ impl HanoiApp {
         self.draw_settings_window(ctx);
         self.draw_replays_window(ctx);
         self.draw_input_display_window(ctx);
+        self.draw_hud(ctx);
+        self.draw_diagnostics(ctx);
+        self.draw_toast(ctx);
+        self.draw_close_prompt(ctx);
 
         if let GameState::Finished(end) = self.state {
-            self.draw_completed_window(ctx, end);
+            if self.completed_window {
+                self.draw_completed_window(ctx, end);
+            }
+        }
+    }
+
+    /// Holds the window open while a run is going, until the player confirms they want to quit.
+    pub fn draw_close_prompt(&mut self, ctx: &egui::Context) {
+        let running = self.player == PlayerKind::Human && matches!(self.state, GameState::Playing(_));
+        if self.confirm_close && running && !self.close_confirmed && ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+            self.close_prompt = true;
+        }
+        if !self.close_prompt { return }
+
+        puffin::profile_function!();
+
+        let modal = Modal::new(Id::new("close_prompt")).show(ctx, |ui| {
+            ui.heading("Quit during a run?");
+            ui.label("The current run will be lost.");
+            ui.horizontal(|ui| {
+                if ui.button("Quit").clicked() {
+                    self.close_confirmed = true;
+                    ui.ctx().send_viewport_cmd(ViewportCommand::Close);
+                }
+                if ui.button("Keep playing").clicked() {
+                    self.close_prompt = false;
+                }
+            });
+        });
+        if modal.should_close() {
+            self.close_prompt = false;
+        }
+    }
+
+    pub fn show_toast(&mut self, text: impl Into<String>) {
+        self.toast = Some((text.into(), Instant::now()));
+    }
+
+    pub fn draw_toast(&mut self, ctx: &egui::Context) {
+        let Some((text, start)) = &self.toast else { return };
+
+        let elapsed = start.elapsed();
+        if elapsed >= TOAST_DURATION {
+            self.toast = None;
+            return;
+        }
+
+        puffin::profile_function!();
+
+        let opacity = 1.0 - elapsed.as_secs_f32() / TOAST_DURATION.as_secs_f32();
+        Area::new(Id::new("toast"))
+            .anchor(Align2::CENTER_BOTTOM, vec2(0.0, -TOAST_MARGIN))
+            .order(Order::Tooltip)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.set_opacity(opacity);
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(text.as_str());
+                });
+            });
+
+        ctx.request_repaint();
+    }
+
+    pub fn draw_celebration(&mut self, ui: &mut Ui) {
+        let Some(start) = self.celebration else { return };
+
+        let elapsed = start.elapsed();
+        if !self.celebrate_highscore || elapsed >= CELEBRATION_DURATION {
+            self.celebration = None;
+            return;
+        }
+
+        puffin::profile_function!();
+
+        let progress = elapsed.as_secs_f32() / CELEBRATION_DURATION.as_secs_f32();
+        let fade = 1.0 - progress;
+        let rect = ui.max_rect();
+        let painter = ui.painter();
+
+        painter.rect_filled(rect, 0.0, HIGHSCORE_COLOR.gamma_multiply(fade * 0.3));
+
+        let disks_count = self.hanoi.disks_count;
+        for i in 0..CELEBRATION_CONFETTI {
+            // cheap deterministic noise, so that every confetto keeps its lane and speed
+            let noise = |salt: f32| ((i as f32 + salt).sin() * 43758.547).fract().abs();
+            let x = rect.left() + noise(0.0) * rect.width();
+            let y = rect.top() + (progress * (0.5 + noise(0.5)) * 1.5 - noise(0.25) * 0.3) * rect.height();
+            let color = self.color_theme.to_color(i % disks_count + 1, disks_count);
+            painter.rect_filled(
+                Rect::from_center_size(Pos2::new(x, y), Vec2::splat(CONFETTI_SIZE)),
+                CONFETTI_SIZE / 4.0,
+                color.gamma_multiply(fade),
+            );
         }
+
+        ui.ctx().request_repaint();
     }
 
     pub fn draw_blindfold(&self, ui: &mut Ui) {
@@ -147,15 +509,16 @@ impl HanoiApp {
     pub fn draw_poles(&mut self, ui: &mut Ui, pointer_pos: Option<Pos2>) -> PolesVec<Response> {
         puffin::profile_function!();
 
-        ui.scope(|ui| {
+        let poles = ui.scope(|ui| {
             let style = ui.style_mut();
             let previous_spacing = style.spacing.item_spacing;
             style.spacing.item_spacing = Vec2::new(0.0, 0.0);
 
             ui.columns(self.hanoi.poles_count, |uis| {
-                uis.iter_mut()
+                let mut poles = uis.iter_mut()
                     .enumerate()
-                    .map(|(i, ui)| {
+                    .map(|(column, ui)| {
+                        let i = pole_column(column, self.hanoi.poles_count, self.mirror_poles);
                         ui.style_mut().spacing.item_spacing = previous_spacing;
                         let pole = self.draw_pole(ui, i).interact(Sense::drag());
                         if let Some(pointer_pos) = pointer_pos {
@@ -163,9 +526,38 @@ impl HanoiApp {
                         }
                         pole
                     })
-                    .collect::<PolesVec<Response>>()
+                    .collect::<PolesVec<Response>>();
+                // the inputs find the poles by their logical index
+                if self.mirror_poles {
+                    poles.reverse();
+                }
+                poles
             })
-        }).inner
+        }).inner;
+        self.draw_hint(ui, &poles);
+        self.draw_cycle_cursor(ui, &poles);
+        poles
+    }
+
+    /// A frame around the pole the cycle keys are on.
+    fn draw_cycle_cursor(&self, ui: &Ui, poles: &PolesVec<Response>) {
+        if self.player != PlayerKind::Human { return }
+        let Some(pole) = self.cycle_pole.and_then(|pole| poles.get(pole)) else { return };
+        ui.painter().rect_stroke(pole.rect.shrink(CYCLE_CURSOR_WIDTH), CYCLE_CURSOR_WIDTH, Stroke::new(CYCLE_CURSOR_WIDTH, HINT_COLOR));
+    }
+
+    /// An arrow from the pole to take the next disk of the optimal path from to the pole to put it on.
+    fn draw_hint(&self, ui: &Ui, poles: &PolesVec<Response>) {
+        if self.blindfold || self.player != PlayerKind::Human { return }
+        let Some((from, to)) = self.hint else { return };
+        let (Some(from), Some(to)) = (poles.get(from), poles.get(to)) else { return };
+
+        let y = match self.poles_position {
+            PolesPosition::Bottom => from.rect.top() + HINT_MARGIN,
+            PolesPosition::Top => from.rect.bottom() - HINT_MARGIN,
+        };
+        let origin = Pos2::new(from.rect.center().x, y);
+        ui.painter().arrow(origin, Vec2::new(to.rect.center().x - origin.x, 0.0), Stroke::new(HINT_WIDTH, HINT_COLOR));
     }
 
     pub fn draw_pole(&mut self, ui: &mut Ui, i: usize) -> Response {
@@ -183,14 +575,30 @@ impl HanoiApp {
                 puffin::profile_scope!("pole_layout");
                 let max_width = ui.available_width();
                 let max_height = ui.available_height();
-                let spacing = ui.style_mut().spacing.item_spacing.y;
-                let disk_height = DISK_HEIGHT.min((max_height - spacing * (self.hanoi.disks_count + 2) as f32) / (self.hanoi.disks_count as f32)).max(0.1);
+                let disks_count = self.hanoi.disks_count as f32;
+                let fit_disk_height = |spacing: f32| (max_height - spacing * (disks_count + 2.0)) / disks_count;
+                let mut spacing = ui.style_mut().spacing.item_spacing.y;
+                // when space is tight, the gaps between disks are the first thing to go
+                if fit_disk_height(spacing) < COMPACT_DISK_HEIGHT {
+                    spacing = 0.0;
+                    ui.style_mut().spacing.item_spacing.y = spacing;
+                }
+                let disk_height = fit_disk_height(spacing).clamp(MIN_DISK_HEIGHT, DISK_HEIGHT);
                 let mut disks_skipped = 0;
+                let glide = self.replay_glide_on(i).or_else(|| self.move_animation_on(i));
+
+                // the ghost's disks are laid out in a child so the live disks go over them in the same slots
+                if let Some(ghost) = &self.ghost {
+                    let mut ghost_ui = ui.new_child(UiBuilder::new().max_rect(ui.available_rect_before_wrap()).layout(*ui.layout()));
+                    for &disk_number in &ghost.game.poles[i] {
+                        self.draw_faded_disk(&mut ghost_ui, disk_number, max_width, disk_height, GHOST_OPACITY);
+                    }
+                }
 
                 self.hanoi.poles[i].iter().enumerate().for_each(|(j, &disk_number)| {
                     let is_drag = self.dragging_pole == Some(i);
                     let is_swift = self.swift_pole == Some(i);
-                    let is_count = is_drag as usize + is_swift as usize;
+                    let is_count = is_drag as usize + is_swift as usize + glide.is_some() as usize;
 
                     if j >= self.hanoi.poles[i].len() - is_count {
                         disks_skipped += 1;
@@ -204,9 +612,28 @@ impl HanoiApp {
                     }
                 });
 
+                if let (Some((from, progress)), Some(&disk_number)) = (glide, self.hanoi.poles[i].last()) {
+                    self.draw_gliding_disk(ui, i, (from, progress), disk_number, max_width, disk_height);
+                }
+
+                let afterimage = self.afterimage_on(i);
+                if let Some((disk_number, opacity)) = afterimage {
+                    self.draw_faded_disk(ui, disk_number, max_width, disk_height, opacity);
+                }
+
+                // fills the free slots of the target pole with the disks that still have to get there
+                let mut target_disks = 0;
+                if self.target_preview && !self.hanoi.spread_goal && self.hanoi.target_pole() == i && afterimage.is_none() {
+                    let visible_disks = self.hanoi.poles[i].len() - disks_skipped;
+                    for j in visible_disks..self.hanoi.disks_count {
+                        self.draw_faded_disk(ui, self.hanoi.disks_count - j, max_width, disk_height, TARGET_PREVIEW_OPACITY);
+                        target_disks += 1;
+                    }
+                }
+
                 if self.show_poles {
                     let single_height = disk_height + spacing;
-                    let pole_size = self.hanoi.poles[i].len();
+                    let pole_size = self.hanoi.poles[i].len() + afterimage.is_some() as usize + target_disks;
                     let remaining_size = self.hanoi.disks_count + disks_skipped - pole_size + 1;
                     let remaining_height = remaining_size as f32 * single_height;
                     let size = vec2(POLE_WIDTH, remaining_height);
@@ -219,6 +646,39 @@ impl HanoiApp {
         ).response
     }
 
+    /// Draws the replayed disk on its way from the top of pole `from` to the top of pole `to`,
+    /// called from inside the layout of pole `to`, after its other disks.
+    fn draw_gliding_disk(&self, ui: &mut Ui, to: usize, (from, progress): (usize, f32), disk_number: usize, max_width: f32, disk_height: f32) {
+        let size = self.calculate_disk_size(disk_number, max_width, disk_height);
+        let slot_height = disk_height + ui.spacing().item_spacing.y;
+        let next_slot = ui.available_rect_before_wrap();
+        let (target_y, direction) = match self.poles_position {
+            PolesPosition::Bottom => (next_slot.bottom() - size.y / 2.0, 1.0),
+            PolesPosition::Top => (next_slot.top() + size.y / 2.0, -1.0),
+        };
+        let target = Pos2::new(ui.max_rect().center().x, target_y);
+        // the columns are all as wide and the disk left from one slot above the top of pole `from`
+        let slots_apart = self.hanoi.poles[from].len() as f32 - (self.hanoi.poles[to].len() - 1) as f32;
+        let source = target + vec2(
+            (self.pole_column(from) as f32 - self.pole_column(to) as f32) * ui.max_rect().width(),
+            -direction * slots_apart * slot_height,
+        );
+        let eased = 1.0 - (1.0 - progress).powi(2);
+
+        Area::new(Id::new("gliding_disk"))
+            .order(Order::Foreground)
+            .interactable(false)
+            .fade_in(false)
+            .fixed_pos(source.lerp(target, eased) - size / 2.0)
+            .show(ui.ctx(), |ui| {
+                self.draw_disk(ui, disk_number, max_width, size.y);
+            });
+    }
+
+    fn pole_column(&self, pole: usize) -> usize {
+        pole_column(pole, self.hanoi.poles_count, self.mirror_poles)
+    }
+
     pub fn draw_pole_hover(&mut self, ui: &mut Ui, pole: &Response, pointer_pos: Pos2) {
         if pole.rect.contains(pointer_pos) {
             Painter::new(ui.ctx().clone(), LayerId::background(), pole.rect)
@@ -235,30 +695,66 @@ impl HanoiApp {
         )
     }
 
+    /// The disk that just left pole `i` and how visible its afterimage still is.
+    pub fn afterimage_on(&self, i: usize) -> Option<(usize, f32)> {
+        let (pole, disk_number, time) = self.last_move?;
+        let elapsed = time.elapsed();
+        (self.afterimage && pole == i && elapsed < AFTERIMAGE_DURATION)
+            .then(|| (disk_number, AFTERIMAGE_OPACITY * (1.0 - elapsed.as_secs_f32() / AFTERIMAGE_DURATION.as_secs_f32())))
+    }
+
     pub fn draw_disk(&self, ui: &mut Ui, disk_number: usize, max_width: f32, disk_height: f32) -> Response {
+        self.draw_faded_disk(ui, disk_number, max_width, disk_height, 1.0)
+    }
+
+    pub fn draw_faded_disk(&self, ui: &mut Ui, disk_number: usize, max_width: f32, disk_height: f32, opacity: f32) -> Response {
         puffin::profile_function!("draw_disk");
 
         let size = self.calculate_disk_size(disk_number, max_width, disk_height);
         let (response, painter) = ui.allocate_painter(size, Sense::hover());
-        let color = self.color_theme.to_color(disk_number, self.hanoi.disks_count);
-        painter.rect_filled(response.rect, disk_height / 2.5, color);
-        if self.disk_number {
+        let color = self.color_theme.to_shuffled_color(disk_number, self.hanoi.disks_count, self.color_seed).gamma_multiply(opacity);
+        let rect = if self.outline { response.rect.shrink(OUTLINE_WIDTH / 2.0) } else { response.rect };
+        let (fill, stroke) = if self.outline { (Color32::TRANSPARENT, Stroke::new(OUTLINE_WIDTH, color)) } else { (color, Stroke::NONE) };
+        match self.disk_shape {
+            DiskShape::Rounded | DiskShape::Rectangle => {
+                let rounding = if self.disk_shape == DiskShape::Rounded { disk_height / 2.5 } else { 0.0 };
+                painter.rect(rect, rounding, fill, stroke);
+            },
+            DiskShape::Trapezoid => {
+                let inset = (rect.height() / 2.0).min(rect.width() / 4.0);
+                let (narrow_y, wide_y) = match self.poles_position {
+                    PolesPosition::Bottom => (rect.top(), rect.bottom()),
+                    PolesPosition::Top => (rect.bottom(), rect.top()),
+                };
+                let points = vec![
+                    Pos2::new(rect.left() + inset, narrow_y),
+                    Pos2::new(rect.right() - inset, narrow_y),
+                    Pos2::new(rect.right(), wide_y),
+                    Pos2::new(rect.left(), wide_y),
+                ];
+                painter.add(Shape::convex_polygon(points, fill, stroke));
+            },
+        }
+        let fitting_size = disk_height / 1.5;
+        let min_size = self.disk_number_settings.min_size as f32;
+        if self.disk_number && !(self.disk_number_settings.hide_when_small && fitting_size < min_size) {
             puffin::profile_scope!("disk_number");
 
             let center_pos = response.rect.center();
             let align = Align2::CENTER_CENTER;
             let disk_number = disk_number.to_string();
-            let number_size = disk_height / 1.5;
+            let number_size = fitting_size.max(min_size);
+            let outline = self.disk_number_settings.outline as f32 * number_size / DISK_NUMBER_OUTLINE_REFERENCE;
 
             for x in -1..=1 {
                 for y in -1..=1 {
-                    if x == 0 || y == 0 { continue }
+                    if x == 0 || y == 0 || outline <= 0.0 { continue }
                     painter.text(
-                        center_pos + vec2(x as f32, y as f32),
+                        center_pos + vec2(x as f32, y as f32) * outline,
                         align,
                         &disk_number,
                         FontId::monospace(number_size),
-                        TEXT_OUTLINE_COLOR,
+                        TEXT_OUTLINE_COLOR.gamma_multiply(opacity),
                     );
                 }
             }
@@ -267,7 +763,7 @@ impl HanoiApp {
                 align,
                 disk_number,
                 FontId::monospace(number_size),
-                TEXT_COLOR,
+                TEXT_COLOR.gamma_multiply(opacity),
             );
         }
         response
@@ -322,16 +818,106 @@ impl HanoiApp {
     pub fn draw_state(&mut self, ui: &mut egui::Ui) {
         puffin::profile_function!();
 
-        ui.label(match self.state {
+        if self.warming_up && self.player == PlayerKind::Human {
+            ui.colored_label(Color32::YELLOW, format!("Warmup, press {:?} to begin", self.begin_key))
+                .on_hover_text("Moves made now don't count, the board goes back to the start when the game begins.");
+        }
+        if let Some(daily) = self.daily {
+            ui.colored_label(Color32::YELLOW, format!("Daily challenge of {} (seed {})", daily.date, daily.seed()));
+        } else if self.scramble {
+            ui.colored_label(Color32::YELLOW, format!("Random start with seed {}, times aren't saved", self.scramble_seed));
+        } else if self.hanoi.custom_start.is_some() {
+            ui.colored_label(Color32::YELLOW, "Practicing a puzzle, times aren't saved");
+        }
+        let state = match self.state {
             GameState::Reset => "Not started".to_string(),
-            GameState::Playing(start) => format!("{:.3?} seconds", start.elapsed().as_secs_f64()),
+            GameState::Playing(start) => format!("{:.3?} seconds", self.playing_elapsed(start).as_secs_f64()),
             GameState::Finished(duration) => {
                 let seconds = duration.as_secs_f64();
                 let small_time = if seconds < 0.001 { format!("({:?})", duration) } else { "".to_string() };
                 format!("{seconds:.3?} seconds {small_time}")
             },
-        });
+        };
+        match self.metronome_on_beat() {
+            Some(true) => ui.colored_label(Color32::GREEN, state),
+            Some(false) => ui.colored_label(Color32::RED, state),
+            None => ui.label(state),
+        };
         ui.label(format!("Moves: {}/{} optimal", self.moves, self.hanoi.required_moves()));
+        if let Some((played, total)) = self.step_bot_progress() {
+            ui.label(format!("Step bot: move {played}/{total}, {:?} for the next one", self.step_key));
+        }
+        if self.show_placed_disks {
+            ui.label(format!("{}/{} placed", self.hanoi.placed_disks(), self.hanoi.disks_count));
+        }
+        if self.player == PlayerKind::Human {
+            let attempts = self.attempts.get(&self.get_current_header()).copied().unwrap_or_default();
+            let attempt = if self.state == GameState::Reset { attempts + 1 } else { attempts };
+            ui.label(format!("Attempt #{attempt}"));
+        }
+        if self.trainer.enabled && self.player == PlayerKind::Human {
+            let target = self.trainer.target(self.hanoi.required_moves())
+                .map_or("no target".to_string(), |target| format!("target {:.3} seconds", target.as_secs_f64()));
+            ui.label(format!("Trainer: {} of {} disks, {target}, {} runs", self.trainer.level, self.trainer.last_level, self.trainer.runs));
+        }
+        if let (GameState::Playing(start), Some(&goal)) = (&self.state, self.goal_times.get(&self.get_current_header())) {
+            let elapsed = start.elapsed();
+            let mut bar = ProgressBar::new((elapsed.as_secs_f32() / goal.as_secs_f32()).min(1.0))
+                .desired_width(GOAL_BAR_WIDTH)
+                .text(format!("Goal: {:.3} seconds", goal.as_secs_f64()));
+            if elapsed > goal {
+                bar = bar.fill(Color32::RED);
+            }
+            ui.add(bar);
+        }
+    }
+
+    pub fn draw_hud(&self, ctx: &egui::Context) {
+        if !self.hud.enabled { return }
+
+        puffin::profile_function!();
+
+        let elapsed = match self.state {
+            GameState::Reset => Duration::ZERO,
+            GameState::Playing(start) => self.playing_elapsed(start),
+            GameState::Finished(duration) => duration,
+        };
+        let required_moves = self.hanoi.required_moves().to_number();
+        let seconds = elapsed.as_secs_f64();
+        let moves_per_second = if seconds > 0.0 { self.moves as f64 / seconds } else { 0.0 };
+        let progress = self.moves as f64 / required_moves as f64 * 100.0;
+
+        let corner = self.hud.corner.align();
+        let font = FontId::monospace(self.hud.font_size as f32);
+
+        Area::new(Id::new("hud"))
+            .anchor(corner, HUD_MARGIN * vec2(-corner.x().to_sign(), -corner.y().to_sign()))
+            .order(Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(Color32::from_black_alpha(self.hud.background_alpha))
+                    .rounding(4.0)
+                    .inner_margin(HUD_MARGIN)
+                    .show(ui, |ui| {
+                        for line in [
+                            format!("{seconds:.3}s"),
+                            format!("{}/{}", self.moves, self.hanoi.required_moves()),
+                            format!("{moves_per_second:.2} m/s {progress:.0}%"),
+                        ] {
+                            ui.label(RichText::new(line).font(font.clone()).color(TEXT_COLOR));
+                        }
+                    });
+            });
+    }
+
+    fn end_pole_help(&self) -> String {
+        let start_pole = self.hanoi.start_pole;
+        match self.hanoi.end_pole {
+            None => format!("The game ends when the tower is rebuilt on any pole other than pole {start_pole}."),
+            Some(end_pole) if end_pole == start_pole => format!("The tower has to leave pole {start_pole} and be rebuilt on it."),
+            Some(end_pole) => format!("The game ends when the tower is rebuilt on pole {end_pole}."),
+        }
     }
 
     pub fn draw_settings_window(&mut self, ctx: &egui::Context) {
@@ -346,51 +932,278 @@ impl HanoiApp {
             let max_disks = if self.extra_mode { MAX_DISKS } else { MAX_DISKS_NORMAL };
             let max_poles = if self.extra_mode { MAX_POLES } else { MAX_POLES_NORMAL };
     
-            ui.add_enabled_ui(!matches!(self.state, GameState::Playing(_)), |ui| {
+            // the daily challenge is the same size for everyone
+            ui.add_enabled_ui(!matches!(self.state, GameState::Playing(_)) && self.daily.is_none(), |ui| {
                 puffin::profile_scope!("hanoi_settings");
 
                 check_changed!(
                     self.soft_reset();
-                    ui.add(Slider::new(&mut self.hanoi.disks_count, 1..=max_disks).text("Disks"));
+                    ui.add(Slider::new(&mut self.hanoi.disks_count, 1..=max_disks).text("Disks"))
+                        .on_hover_text("How many disks the tower has. High scores are kept separately for each count.");
                     {
-                        let resp = ui.add(Slider::new(&mut self.hanoi.poles_count, 2..=max_poles).text("Poles"));
+                        let resp = ui.add(Slider::new(&mut self.hanoi.poles_count, 2..=max_poles).text("Poles"))
+                            .on_hover_text("How many poles there are. More poles make the tower take fewer moves.");
                         if resp.changed {
                             self.hanoi.start_pole = self.hanoi.start_pole.min(self.hanoi.poles_count);
+                            self.hanoi.end_pole = self.hanoi.end_pole.map(|end_pole| end_pole.min(self.hanoi.poles_count));
                         }
                         resp
                     };
-                    ui.add(Slider::new(&mut self.hanoi.start_pole, 1..=self.hanoi.poles_count).text("Start pole"));
+                    ui.add(Slider::new(&mut self.hanoi.start_pole, 1..=self.hanoi.poles_count).text("Start pole"))
+                        .on_hover_text("The pole the tower is stacked on when the game starts, counting from the left.");
                 );
 
                 ui.add_space(10.0);
     
-                let mut any_pole = self.hanoi.end_pole.is_none();
-                ui.checkbox(&mut any_pole, "Any end pole");
-                if any_pole {
-                    self.hanoi.end_pole = None;
-                } else {
-                    let end_pole = self.hanoi.end_pole.get_or_insert(1);
-                    ui.add(Slider::new(end_pole, 1..=self.hanoi.poles_count).text("End pole"));
-                };
+                if !self.hanoi.spread_goal {
+                    let mut any_pole = self.hanoi.end_pole.is_none();
+                    ui.checkbox(&mut any_pole, "Any end pole")
+                        .on_hover_text(self.end_pole_help());
+                    if any_pole {
+                        self.hanoi.end_pole = None;
+                    } else {
+                        let help = self.end_pole_help();
+                        let end_pole = self.hanoi.end_pole.get_or_insert(HanoiGame::default_end_pole(self.hanoi.start_pole, self.hanoi.poles_count));
+                        ui.add(Slider::new(end_pole, 1..=self.hanoi.poles_count).text("End pole"))
+                            .on_hover_text(help);
+                        if self.hanoi.end_pole == Some(self.hanoi.start_pole) && self.hanoi.is_valid_goal() {
+                            ui.colored_label(Color32::YELLOW, "The end pole is the start pole");
+                        }
+                    };
+                }
+                if !self.hanoi.is_valid_goal() {
+                    let warning = if self.hanoi.end_pole == Some(self.hanoi.start_pole) {
+                        "Warning: The tower starts on the end pole. Pick another end pole, or use merge start."
+                    } else {
+                        "Warning: The start or end pole isn't on the board. Pick them again."
+                    };
+                    ui.colored_label(Color32::RED, warning);
+                }
     
                 check_changed!(
                     self.soft_reset();
-                    ui.checkbox(&mut self.hanoi.illegal_moves, "Illegal moves");
-                    ui.checkbox(&mut self.blindfold, "Blindfold");
+                    ui.checkbox(&mut self.hanoi.spread_goal, "Spread goal")
+                        .on_hover_text("Instead of rebuilding the tower, finish with every disk alone on a pole other than the start one.");
+                    ui.checkbox(&mut self.hanoi.merge_start, "Merge start")
+                        .on_hover_text("Start with the odd disks on the start pole and the even ones on the next pole, and merge them into one tower.");
+                    ui.checkbox(&mut self.hanoi.illegal_moves, "Illegal moves")
+                        .on_hover_text("Allow placing a larger disk on a smaller one. Scores are kept apart from normal games.");
+                    ui.checkbox(&mut self.blindfold, "Blindfold")
+                        .on_hover_text("Hide the poles while playing. Scores are kept apart from normal games.");
+                    ui.checkbox(&mut self.outline, "Outlines only")
+                        .on_hover_text("Draw the disks without fill, only their outlines. Scores are kept apart from normal games.");
+                );
+
+                if self.hanoi.illegal_moves {
+                    check_changed!(
+                        self.soft_reset();
+                        set_enum_setting(ui, &mut self.hanoi.illegal_counting)
+                            .on_hover_text(ILLEGAL_COUNTING_HELP);
+                    );
+                }
+
+                check_changed!(
+                    self.soft_reset();
+                    ui.checkbox(&mut self.hanoi.multi_move, "Multi-moves")
+                        .on_hover_text("Move the largest group of top disks that fits on the destination at once. Scores are kept apart from normal games.");
                 );
+                if self.hanoi.multi_move {
+                    check_changed!(
+                        self.soft_reset();
+                        set_enum_setting(ui, &mut self.hanoi.multi_move_counting)
+                            .on_hover_text(MULTI_MOVE_COUNTING_HELP);
+                    );
+                }
+            });
+            ui.checkbox(&mut self.show_poles, "Show poles")
+                .on_hover_text("Draw the poles behind the disks.");
+            ui.checkbox(&mut self.disk_number, "Disk number")
+                .on_hover_text("Write the size of each disk on it.");
+            if self.disk_number {
+                ui.collapsing("Disk number style", |ui| {
+                    ui.add(Slider::new(&mut self.disk_number_settings.min_size, 4..=32).text("Minimum font size"));
+                    ui.checkbox(&mut self.disk_number_settings.hide_when_small, "Hide on thin disks")
+                        .on_hover_text("Leave out the numbers that would overflow disks thinner than the minimum font size.");
+                    ui.add(Slider::new(&mut self.disk_number_settings.outline, 0..=4).text("Outline thickness"));
+                });
+            }
+            ui.checkbox(&mut self.afterimage, "Afterimage of the last move")
+                .on_hover_text("Briefly show a faded copy of the last moved disk where it came from.");
+            ui.checkbox(&mut self.target_preview, "Target preview")
+                .on_hover_text("Show a faint tower on the pole the disks have to end on.");
+            ui.checkbox(&mut self.smooth_replays, "Smooth replays")
+                .on_hover_text("Animate the disks of replays between poles. Turn it off to see every move land at its recorded time.");
+            ui.horizontal(|ui| {
+                let mut millis = self.animation_duration.as_millis() as u64;
+                if ui.add(Slider::new(&mut millis, 0..=MAX_ANIMATION_MILLIS).text("Move animation").suffix("ms"))
+                    .on_hover_text("How long a moved disk takes to glide to its pole, 0 makes it jump at once.")
+                    .changed()
+                {
+                    self.animation_duration = Duration::from_millis(millis);
+                }
+            });
+            ui.checkbox(&mut self.adaptive_replays, "Adaptive replay speed")
+                .on_hover_text("Slow replays down where the moves are fast and speed them up where they are slow, for an even pace. Applies to the next replay.");
+
+            if ui.checkbox(&mut self.warmup, "Warmup before every game")
+                .on_hover_text("Make throwaway moves before a game, it only begins when the begin key is pressed.")
+                .changed()
+            {
+                self.soft_reset();
+            }
+            ui.checkbox(&mut self.reset_on_invalid_move, "Reset on invalid move")
+                .on_hover_text("Restart the game as soon as a move is not allowed, instead of ignoring it.");
+            set_enum_setting(ui, &mut self.reset_mode)
+                .on_hover_text("What the reset key and button keep: nothing, the attempt count, or the running clock. Games played across continuous resets don't get saved.");
+            if self.reset_mode == ResetMode::Continuous {
+                ui.colored_label(Color32::YELLOW, "Continuous practice sessions aren't recorded, their times are never saved as high scores or replays");
+            }
+            ui.checkbox(&mut self.celebrate_highscore, "Celebrate new high scores")
+                .on_hover_text("Flash the screen and throw confetti when you beat your best time.");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.sound_enabled, "Sound effects")
+                    .on_hover_text("Play a sound when taking a disk, when dropping it and when a move isn't allowed.");
+                ui.add_enabled(self.sound_enabled, Slider::new(&mut self.volume, 0.0..=1.0)
+                    .text("Volume")
+                    .custom_formatter(|volume, _| format!("{:.0}%", volume * 100.0))
+                    .custom_parser(|text| text.trim_end_matches('%').trim().parse::<f64>().ok().map(|percent| percent / 100.0)));
+            });
+            ui.horizontal(|ui| {
+                let mut metronome = self.metronome_bpm.is_some();
+                ui.checkbox(&mut metronome, "Metronome")
+                    .on_hover_text("Tick at a steady pace while playing. The time turns green when the last move was on beat, red otherwise.");
+                if metronome {
+                    let bpm = self.metronome_bpm.get_or_insert(DEFAULT_METRONOME_BPM);
+                    ui.add(DragValue::new(bpm).range(MIN_METRONOME_BPM..=MAX_METRONOME_BPM).suffix(" BPM"));
+                } else {
+                    self.metronome_bpm = None;
+                }
+            });
+            ui.checkbox(&mut self.sonification, "Sonification")
+                .on_hover_text("Play a tone for every pole and disk size when selecting and moving, so the game can be played by ear.");
+            if (self.sound_enabled || self.metronome_bpm.is_some() || self.sonification) && !audio_available() {
+                ui.colored_label(Color32::YELLOW, "No audio output was found, sounds can't be heard");
+            }
+            ui.checkbox(&mut self.confirm_close, "Confirm closing during a run")
+                .on_hover_text("Ask before closing the window while a game is in progress.");
+            if ui.checkbox(&mut self.show_hint, "Show hints")
+                .on_hover_text("Point from the pole to take a disk from to the pole to put it on, along the optimal path. Hidden while blindfolded and in replays.")
+                .changed()
+            {
+                self.update_hint();
+            }
+            ui.checkbox(&mut self.ghost_enabled, "Race your best")
+                .on_hover_text("Play the best run of the current settings faded behind your game. Not shown while blindfolded.");
+            ui.checkbox(&mut self.show_placed_disks, "Show placed disks")
+                .on_hover_text("Count the disks already in their final place, stacked from the bottom of the target pole.");
+            ui.checkbox(&mut self.dim_completed, "Dim the board when finishing")
+                .on_hover_text("Darken everything behind the completed window, to focus on the results.");
+            ui.checkbox(&mut self.auto_share, "Copy the share text when finishing")
+                .on_hover_text("Put the result in the clipboard as soon as a game ends, without pressing Share.");
+
+            ui.horizontal(|ui| {
+                let mut limit_history = self.move_history_limit.is_some();
+                ui.checkbox(&mut limit_history, "Limit move history")
+                    .on_hover_text("Only keep the latest moves of a game. Replays of truncated games can't be watched.");
+                if limit_history {
+                    let limit = self.move_history_limit.get_or_insert(DEFAULT_MOVE_HISTORY_LIMIT);
+                    ui.add(DragValue::new(limit).range(MIN_MOVE_HISTORY_LIMIT..=usize::MAX).suffix(" moves"));
+                } else {
+                    self.move_history_limit = None;
+                }
             });
-            ui.checkbox(&mut self.show_poles, "Show poles");
-            ui.checkbox(&mut self.disk_number, "Disk number");
 
-            ui.checkbox(&mut self.reset_on_invalid_move, "Reset on invalid move");
+            ui.collapsing("Backups", |ui| {
+                ui.checkbox(&mut self.backup.auto, "Back up daily")
+                    .on_hover_text("Back up the save the first time the app is launched every day, instead of only with --backup.");
+                ui.checkbox(&mut self.backup.compress, "Compress backups")
+                    .on_hover_text("Write the backups gzip compressed. Compressed backups can be restored with --restore too.");
+                ui.horizontal(|ui| {
+                    ui.label("Keep");
+                    ui.add(DragValue::new(&mut self.backup.retention).range(0..=1000).suffix(" backups"))
+                        .on_hover_text("The oldest backups past this count get deleted, 0 keeps all of them.");
+                });
+            });
 
             ui.add_space(10.0);
 
-            set_enum_setting(ui, &mut self.color_theme);
+            self.draw_color_theme_settings(ui);
+            draw_theme_preview(ui, &self.color_theme, self.hanoi.disks_count, THEME_PREVIEW_HEIGHT);
+            set_enum_setting(ui, &mut self.disk_shape);
             set_enum_setting(ui, &mut self.poles_position);
+            if ui.checkbox(&mut self.shuffle_colors, "Shuffle colors")
+                .on_hover_text("Give the disks different colors every game, so that the colors can't be memorized. Scores aren't kept apart.")
+                .changed()
+            {
+                self.shuffle_color_seed();
+            }
+            ui.checkbox(&mut self.mirror_poles, "Mirror poles")
+                .on_hover_text("Draw the poles from right to left. Pole numbers, quick keys and swift keys stay the same.");
+            set_enum_setting(ui, &mut self.share_time_format);
+
+            ui.collapsing("Share preview", |ui| {
+                ui.label(draw_share_tower(&self.color_theme, self.poles_position));
+            });
+
+            ui.collapsing("HUD", |ui| {
+                ui.checkbox(&mut self.hud.enabled, "Show HUD");
+                set_enum_setting(ui, &mut self.hud.corner);
+                ui.add(Slider::new(&mut self.hud.font_size, 8..=64).text("Font size"));
+                ui.add(Slider::new(&mut self.hud.background_alpha, 0..=255).text("Background opacity"));
+            });
+
+            ui.checkbox(&mut self.diagnostics.enabled, "Show diagnostics")
+                .on_hover_text("Shows the frame rate and how long drawing the poles takes.");
+
+            ui.collapsing("App colors", |ui| {
+                puffin::profile_scope!("app_colors_settings");
+
+                let app_theme = self.app_theme;
+                self.app_theme.radio_buttons(ui);
+                if self.app_theme != app_theme {
+                    ui.ctx().set_theme(self.app_theme);
+                }
+
+                let visuals = ui.visuals().clone();
+                check_changed!(
+                    self.app_visuals.apply(ui.ctx());
+                    optional_color_input(ui, "Accent", &mut self.app_visuals.accent, visuals.selection.bg_fill);
+                    optional_color_input(ui, "Panel background", &mut self.app_visuals.panel_fill, visuals.panel_fill);
+                    optional_color_input(ui, "Window background", &mut self.app_visuals.window_fill, visuals.window_fill);
+                );
+            });
     
             ui.add_space(10.0);
 
+            ui.collapsing("Completed window", |ui| {
+                let stats = &mut self.completed_stats;
+                ui.checkbox(&mut stats.optimal, "Optimal solution");
+                ui.checkbox(&mut stats.moves_per_second, "Average moves per second");
+                ui.checkbox(&mut stats.optimal_moves_per_second, "Average optimal moves per second");
+                ui.checkbox(&mut stats.consistency, "Consistency");
+                ui.checkbox(&mut stats.inputs, "Input methods");
+                ui.checkbox(&mut stats.efficiency, "Move efficiency")
+                    .on_hover_text("Share of moves that got closer to the end, only available with 3 poles and no illegal moves");
+                ui.checkbox(&mut stats.rank, "Rank");
+                ui.checkbox(&mut stats.best_time, "Best time");
+            });
+
+            ui.add_space(10.0);
+
+            ui.collapsing("Ways to play", |ui| {
+                for source in InputSource::ALL {
+                    let mut enabled = self.is_human_play_enabled(source);
+                    if ui.checkbox(&mut enabled, source.label()).changed() {
+                        self.set_human_play_enabled(source, enabled);
+                    }
+                }
+                if !InputSource::ALL.iter().any(|&source| self.is_human_play_enabled(source)) {
+                    ui.colored_label(Color32::YELLOW, "Every way to play is off, only the bot can play");
+                }
+            });
+
+            ui.add_space(10.0);
+
             ui.collapsing("Hotkeys", |ui| {
                 puffin::profile_scope!("hotkeys_settings");
 
@@ -398,22 +1211,58 @@ impl HanoiApp {
                     ui.label("Undo");
                     key_input(ui, &mut self.undo_key);
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Redo");
+                    key_input(ui, &mut self.redo_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Move the cycle cursor");
+                    key_input(ui, &mut self.cycle_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Pick the pole under the cycle cursor");
+                    key_input(ui, &mut self.confirm_key);
+                });
                 ui.horizontal(|ui| {
                     ui.label("Reset");
                     key_input(ui, &mut self.reset_key);
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Rematch");
+                    key_input(ui, &mut self.rematch_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Begin after the warmup");
+                    key_input(ui, &mut self.begin_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Start or stop the bot");
+                    key_input(ui, &mut self.bot_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Toggle disk numbers");
+                    key_input(ui, &mut self.disk_number_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Next step bot move");
+                    key_input(ui, &mut self.step_key);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Pause or resume a replay");
+                    key_input(ui, &mut self.pause_key);
+                });
 
                 ui.label("Quick keys");
                 
-                self.quick_keys.retain(|(key, _, _)| !matches!(key, Key::Backspace | Key::Delete));
+                self.quick_keys.retain(|(input, _, _)| !matches!(input, QuickInput::Key(Key::Backspace | Key::Delete)));
 
-                Dnd::new(ui, "dnd_quick_keys").show_vec(&mut self.quick_keys, |ui, (key, from, to), handle, _state| {
+                Dnd::new(ui, "dnd_quick_keys").show_vec(&mut self.quick_keys, |ui, (input, from, to), handle, _state| {
                     puffin::profile_scope!("hotkey_line");
                     ui.horizontal(|ui| {
                         handle.ui(ui, |ui| {
-                            key_input(ui, key);
-                            integer_input(ui, from, self.extra_mode);
-                            integer_input(ui, to, self.extra_mode);
+                            quick_input(ui, input);
+                            integer_input(ui, from, 1..=max_poles);
+                            integer_input(ui, to, 1..=max_poles);
                         });
                     });
                 });
@@ -422,22 +1271,193 @@ impl HanoiApp {
                     if ui.button("+").clicked() && !self.quick_keys.contains(&DEFAULT_QUICK_KEY) {
                         self.quick_keys.push(DEFAULT_QUICK_KEY);
                     }
-                    ui.label("Input Del or Backspace in the key input to remove it");
+                    ui.label("Input Del or Backspace in the key input to remove it, mouse buttons other than the left one can be bound too");
                 });
-            });
-
-            ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    let dialog = || rfd::FileDialog::new()
+                        .add_filter("Quick keys", &["ron", "json"])
+                        .set_file_name("quick_keys.ron");
+
+                    if ui.button("Load from file").clicked() {
+                        if let Some(path) = dialog().pick_file() {
+                            match load_quick_keys(&path) {
+                                Ok(quick_keys) => {
+                                    self.quick_keys = quick_keys;
+                                    self.quick_keys_error = None;
+                                },
+                                Err(err) => self.quick_keys_error = Some(err),
+                            }
+                        }
+                    }
+                    if ui.button("Save to file").clicked() {
+                        if let Some(path) = dialog().save_file() {
+                            self.quick_keys_error = save_quick_keys(&path, &self.quick_keys).err();
+                        }
+                    }
+                });
+
+                if let Some(err) = &self.quick_keys_error {
+                    ui.colored_label(Color32::RED, err);
+                }
+                let out_of_range = out_of_range_quick_keys(&self.quick_keys, self.hanoi.poles_count);
+                if !out_of_range.is_empty() {
+                    let keys = out_of_range.iter().map(|(key, from, to)| format!("{key}: {from} → {to}")).collect::<Vec<_>>().join(", ");
+                    ui.colored_label(Color32::YELLOW, format!("These quick keys use poles past {}, they do nothing: {keys}", self.hanoi.poles_count));
+                }
+
+                ui.label("Swift keys");
+
+                for (pole, key) in self.swift_keys.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Pole {}", pole + 1));
+                        key_input(ui, key);
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.swift_keys.len() < MAX_POLES, Button::new("+")).clicked() {
+                        self.swift_keys.push(SWIFT_KEYS.get(self.swift_keys.len()).copied().unwrap_or(Key::Num0));
+                    }
+                    if ui.add_enabled(!self.swift_keys.is_empty(), Button::new("-")).clicked() {
+                        self.swift_keys.pop();
+                    }
+                    if ui.button("Reset to defaults").clicked() {
+                        self.swift_keys = SWIFT_KEYS.to_vec();
+                    }
+                });
+                if self.swift_keys.len() < self.hanoi.poles_count {
+                    ui.colored_label(Color32::YELLOW, format!("Poles past {} can't be selected with swift keys", self.swift_keys.len()));
+                }
+
+                ui.label(format!("Gamepad ({} connected)", connected_gamepads()));
+
+                let binding = self.gamepad_binding;
+                let mut gamepad_input = |ui: &mut Ui, label: String, action: GamepadAction, input: GamepadInput| {
+                    ui.horizontal(|ui| {
+                        ui.label(label);
+                        let text = if binding == Some(action) { "Press a button…".to_string() } else { input.to_string() };
+                        if ui.button(text).clicked() {
+                            self.gamepad_binding = (binding != Some(action)).then_some(action);
+                        }
+                    });
+                };
+                for (pole, &input) in self.gamepad.poles.iter().enumerate() {
+                    gamepad_input(ui, format!("Pole {}", pole + 1), GamepadAction::Pole(pole), input);
+                }
+                gamepad_input(ui, "Undo".to_string(), GamepadAction::Undo, self.gamepad.undo);
+                gamepad_input(ui, "Reset".to_string(), GamepadAction::Reset, self.gamepad.reset);
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.gamepad.poles.len() < MAX_POLES, Button::new("+")).clicked() {
+                        self.gamepad.poles.push(GamepadInput::UNBOUND);
+                    }
+                    if ui.add_enabled(!self.gamepad.poles.is_empty(), Button::new("-")).clicked() {
+                        self.gamepad.poles.pop();
+                        self.gamepad_binding = None;
+                    }
+                    if ui.button("Reset to defaults").clicked() {
+                        self.gamepad = GamepadSettings { deadzone: self.gamepad.deadzone, ..Default::default() };
+                        self.gamepad_binding = None;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Stick deadzone");
+                    ui.add(Slider::new(&mut self.gamepad.deadzone, 5..=95).suffix("%"));
+                });
+            });
+
+            ui.add_space(10.0);
 
             ui.add_enabled_ui(!matches!(self.state, GameState::Playing(_)) && !self.equal_settings(&DEFAULT_HANOI_APP), |ui| {
                 puffin::profile_scope!("default_settings");
 
                 if ui.button("Default Settings").double_clicked() {
                     let highscores = self.highscores.clone();
+                    let attempts = self.attempts.clone();
+                    let goal_times = self.goal_times.clone();
+                    let lifetime_stats = self.lifetime_stats;
                     *self = (*DEFAULT_HANOI_APP).clone();
                     self.highscores = highscores;
+                    self.attempts = attempts;
+                    self.goal_times = goal_times;
+                    self.lifetime_stats = lifetime_stats;
+                    ui.ctx().set_theme(self.app_theme);
+                    self.app_visuals.apply(ui.ctx());
                 }
             });
     
+            ui.horizontal(|ui| {
+                let header = self.get_current_header();
+                let mut has_goal = self.goal_times.contains_key(&header);
+                ui.checkbox(&mut has_goal, "Goal time");
+                if has_goal {
+                    let goal = self.goal_times.entry(header).or_insert(DEFAULT_GOAL_TIME);
+                    let mut seconds = goal.as_secs_f64();
+                    if ui.add(DragValue::new(&mut seconds).range(0.001..=f64::MAX).speed(0.1).suffix(" seconds")).changed() {
+                        *goal = Duration::from_secs_f64(seconds);
+                    }
+                } else {
+                    self.goal_times.remove(&header);
+                }
+            });
+
+            ui.add_enabled_ui(!matches!(self.state, GameState::Playing(_)), |ui| {
+                ui.collapsing("Trainer", |ui| {
+                    if ui.checkbox(&mut self.trainer.enabled, "Disk count trainer")
+                        .on_hover_text("Start from a few disks and get one more every time you finish under the target time. The disk count is set by the trainer while it's on.")
+                        .changed()
+                    {
+                        self.trainer.start();
+                        self.soft_reset();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("From");
+                        let max_disks = if self.extra_mode { MAX_DISKS } else { MAX_DISKS_NORMAL };
+                        let from = integer_input(ui, &mut self.trainer.first_level, 1..=max_disks);
+                        ui.label("to");
+                        let to = integer_input(ui, &mut self.trainer.last_level, self.trainer.first_level..=max_disks);
+                        ui.label("disks");
+                        if (from.changed() || to.changed()) && self.trainer.enabled {
+                            self.trainer.start();
+                            self.soft_reset();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let mut millis = self.trainer.time_per_move.as_millis() as u64;
+                        if integer_input(ui, &mut millis, 1..=10_000).changed() {
+                            self.trainer.time_per_move = Duration::from_millis(millis);
+                        }
+                        ui.label("ms per optimal move");
+                    });
+                });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Player");
+                if ui.radio(self.player == PlayerKind::Human, "Human").clicked() && self.player != PlayerKind::Human {
+                    self.soft_reset();
+                }
+                if ui.radio(self.player == PlayerKind::Bot, "Bot").on_hover_text("Watch the bot solve the current settings").clicked() && self.player != PlayerKind::Bot {
+                    self.toggle_bot();
+                }
+                let stepping = matches!(self.player, PlayerKind::StepBot(..));
+                if ui.radio(stepping, "Step bot")
+                    .on_hover_text(format!("Play the bot's moves one at a time with {:?}, the game is timed and saved like any other", self.step_key))
+                    .clicked() && !stepping
+                {
+                    self.toggle_step_bot();
+                }
+                // replays are started from the replays window
+                if matches!(self.player, PlayerKind::Replay(..)) {
+                    ui.add_enabled(false, egui::RadioButton::new(true, "Replay"));
+                }
+            });
+
+            ui.collapsing("Config code", |ui| self.draw_config_code(ui));
+            ui.collapsing("Puzzles", |ui| self.draw_puzzles(ui));
+            ui.collapsing("Daily challenge", |ui| self.draw_daily(ui));
+
             let highscore = self.get_highscores_entry(self.get_current_header()).first();
             if let Some(highscore) = highscore {
                 ui.label(format!("Your high score for these settings: {:.3?} seconds", highscore.time.as_secs_f64()));
@@ -451,6 +1471,63 @@ impl HanoiApp {
         self.settings_window = settings_window;
     }
 
+    /// Like `set_enum_setting`, with an editor for the custom theme's colors.
+    fn draw_color_theme_settings(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        ComboBox::from_label("ColorTheme")
+            .selected_text(self.color_theme.name())
+            .show_ui(ui, |ui| {
+                for theme in ColorTheme::iter() {
+                    let selected = theme.name() == self.color_theme.name();
+                    if ui.selectable_label(selected, theme.name()).clicked() && !selected {
+                        self.color_theme = match theme {
+                            ColorTheme::Custom(_) => self.color_theme.to_custom(self.hanoi.disks_count),
+                            theme => theme,
+                        };
+                    }
+                }
+            });
+
+        ui.collapsing("Paste a palette", |ui| {
+            ui.text_edit_singleline(&mut self.palette_text)
+                .on_hover_text("Hex colors like #FF2BFE, #FEFEFE, #72FDFF, separated by commas or spaces");
+            if self.palette_text.trim().is_empty() { return }
+            match parse_hex_palette(&self.palette_text) {
+                Ok(colors) => {
+                    draw_theme_preview(ui, &ColorTheme::Custom(colors.clone()), colors.len(), THEME_PREVIEW_HEIGHT);
+                    if ui.button("Use as custom theme").clicked() {
+                        self.color_theme = ColorTheme::Custom(colors);
+                        self.palette_text.clear();
+                    }
+                },
+                Err(err) => { ui.colored_label(Color32::RED, err.to_string()); },
+            }
+        });
+
+        let ColorTheme::Custom(colors) = &mut self.color_theme else { return };
+
+        let mut remove = None;
+        // colors can repeat, so the indices are the ids
+        Dnd::new(ui, "dnd_custom_colors").show(0..colors.len(), |ui, i, handle, _state| {
+            ui.horizontal(|ui| {
+                handle.ui(ui, |ui| {
+                    ui.label(format!("{}", i + 1));
+                });
+                ui.color_edit_button_srgba(&mut colors[i]);
+                if ui.button("-").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }).update_vec(colors);
+        if let Some(i) = remove {
+            colors.remove(i);
+        }
+        if ui.button("+").on_hover_text("Add a color, the colors repeat from the smallest disk").clicked() {
+            colors.push(colors.last().copied().unwrap_or(Color32::WHITE));
+        }
+    }
+
     pub fn draw_input_display_window(&mut self, ctx: &egui::Context) {
         puffin::profile_function!();
 
@@ -460,9 +1537,18 @@ impl HanoiApp {
             .open(&mut input_display_window)
             .auto_sized()
             .show(ctx, |ui| {
+                let replaying = matches!(self.player, PlayerKind::Replay(..));
+                ui.add_enabled(replaying, Checkbox::new(&mut self.replay_inputs, "Show replay inputs"))
+                    .on_hover_text("Light up the inputs that made every move of the replay, to learn how it was played.");
+
+                if replaying && self.replay_inputs {
+                    self.draw_replay_inputs(ui);
+                    return;
+                }
+
                 let (qk, reset, undo) = ctx.input(|i| {
                     (
-                        self.quick_keys.iter().map(|(key, _, _)| i.key_down(*key)).collect::<Vec<bool>>(),
+                        self.quick_keys.iter().map(|(input, _, _)| input.down(i)).collect::<Vec<bool>>(),
                         i.key_down(self.reset_key),
                         i.key_down(self.undo_key),
                     )
@@ -473,13 +1559,103 @@ impl HanoiApp {
                         input_display_key(ui, key, qk[i]);
                     }
                 });
-                input_display_key(ui, self.reset_key, reset);
-                input_display_key(ui, self.undo_key, undo);
+                input_display_key(ui, self.reset_key.into(), reset);
+                input_display_key(ui, self.undo_key.into(), undo);
             });
 
         self.input_display_window = input_display_window;
     }
 
+    /// The input display driven by the replayed moves: the quick key, the swift keys or the drag that made the last one.
+    fn draw_replay_inputs(&self, ui: &mut Ui) {
+        let input = self.replay_input();
+        let lit = |source: InputSource| input.filter(|&(input, _, _)| input == source).map(|(_, from, to)| (from, to));
+
+        let quick_key = lit(InputSource::QuickKeys);
+        ui.horizontal_wrapped(|ui| {
+            for &(key, from, to) in &self.quick_keys {
+                input_display_key(ui, key, quick_key == Some((from - 1, to - 1)));
+            }
+        });
+
+        let swift_poles = lit(InputSource::SwiftKeys);
+        ui.horizontal_wrapped(|ui| {
+            for (pole, &key) in self.swift_keys.iter().enumerate().take(self.hanoi.poles_count) {
+                input_display_key(ui, key.into(), swift_poles.is_some_and(|(from, to)| pole == from || pole == to));
+            }
+        });
+
+        let drag = lit(InputSource::DragAndDrop);
+        let drag_text = drag.map_or("🖱 Drag".to_string(), |(from, to)| format!("🖱 Drag {} → {}", from + 1, to + 1));
+        let button = ui.button(drag_text);
+        if drag.is_some() {
+            button.highlight();
+        }
+
+        let cycle = lit(InputSource::CycleKeys);
+        let cycle_text = cycle.map_or("⟳ Cycle".to_string(), |(from, to)| format!("⟳ Cycle {} → {}", from + 1, to + 1));
+        let button = ui.button(cycle_text);
+        if cycle.is_some() {
+            button.highlight();
+        }
+
+        let gamepad = lit(InputSource::GamepadPlay);
+        let gamepad_text = gamepad.map_or("🎮 Gamepad".to_string(), |(from, to)| format!("🎮 Gamepad {} → {}", from + 1, to + 1));
+        let button = ui.button(gamepad_text);
+        if gamepad.is_some() {
+            button.highlight();
+        }
+    }
+
+    /// Every way to play with the keys currently bound to it.
+    fn draw_controls_help(&self, ui: &mut Ui) {
+        let poles = self.hanoi.poles_count;
+
+        ui.collapsing("Quick keys", |ui| {
+            ui.label("Every key moves the top disk from one pole to another.");
+            for (key, from, to) in self.quick_keys.iter().filter(|(_, from, to)| *from <= poles && *to <= poles) {
+                ui.monospace(format!("{key}: {from} → {to}"));
+            }
+        });
+        ui.collapsing("Swift keys", |ui| {
+            ui.label("Press the pole to take a disk from, then the pole to put it on.");
+            for (pole, key) in self.swift_keys.iter().enumerate().take(poles) {
+                ui.monospace(format!("{key:?}: pole {}", pole + 1));
+            }
+        });
+        ui.collapsing("Drag and drop", |ui| {
+            ui.label("Drag a disk with the mouse and drop it on another pole.");
+        });
+        ui.collapsing("Cycle keys", |ui| {
+            ui.label("Move the cursor to a pole and pick it to take a disk from it, then move the cursor to the pole to put it on and pick that one.");
+            ui.monospace(format!("{:?}: move the cursor to the next pole", self.cycle_key));
+            ui.monospace(format!("{:?}: pick the pole under the cursor", self.confirm_key));
+        });
+        ui.collapsing("Gamepad", |ui| {
+            ui.label("Press the button of the pole to take a disk from, then the one of the pole to put it on.");
+            for (pole, input) in self.gamepad.poles.iter().enumerate().take(poles) {
+                ui.monospace(format!("{input}: pole {}", pole + 1));
+            }
+            ui.monospace(format!("{}: Undo", self.gamepad.undo));
+            ui.monospace(format!("{}: Reset", self.gamepad.reset));
+        });
+        ui.collapsing("Other keys", |ui| {
+            for (action, key) in [
+                ("Reset", self.reset_key),
+                ("Undo", self.undo_key),
+                ("Redo", self.redo_key),
+                ("Rematch", self.rematch_key),
+                ("Start or stop the bot", self.bot_key),
+                ("Toggle disk numbers", self.disk_number_key),
+            ] {
+                ui.monospace(format!("{key:?}: {action}"));
+            }
+            if self.warmup {
+                ui.monospace(format!("{:?}: Begin after the warmup", self.begin_key));
+            }
+        });
+    }
+
     pub fn draw_infos_panel(&mut self, ctx: &egui::Context) {
         if !self.infos_panel { return }
 
@@ -487,7 +1663,7 @@ impl HanoiApp {
 
         SidePanel::new(Side::Right, "infos_panel")
             .width_range(200.0..=600.0)
-            .default_width(600.0)
+            .default_width(INFOS_PANEL_WIDTH)
             .show(ctx, |ui| {
                 let width = ui.fonts(|f|f.glyph_width(&TextStyle::Body.resolve(ui.style()), ' '));
                 ui.spacing_mut().item_spacing.x = width;
@@ -503,8 +1679,8 @@ impl HanoiApp {
                 // todo: these two should update depending on the settings
                 ui.label("Your goal is to move all disks to a different pole.");
                 ui.label("You can only move one disk at a time, and you cannot place a larger disk on top of a smaller one.");
-                // todo: maybe add a trait for different inputs, so that this updates automatically
-                ui.label("There are three ways to control this game.");
+                ui.label(format!("There are {HUMAN_PLAY_COUNT} ways to control this game."));
+                self.draw_controls_help(ui);
 
                 ui.horizontal_wrapped(|ui| {
                     ui.label("Join the developer's");
@@ -532,7 +1708,20 @@ impl HanoiApp {
                 for (label, _) in TIME_ESTIMATIONS {
                     ui.label(format!("Estimated time for {}: ∞", label));
                 }
-                ui.colored_label(Color32::RED, "Warning: Game is impossible. Increase the number of stacks or decrease the number of disks.");
+                if self.hanoi.spread_goal && self.hanoi.multi_move && self.hanoi.disks_count < self.hanoi.poles_count {
+                    ui.colored_label(Color32::RED, "Warning: Game is impossible. Multi-moves never split the tower, turn them off to spread it.");
+                } else {
+                    ui.colored_label(Color32::RED, "Warning: Game is impossible. Increase the number of stacks or decrease the number of disks.");
+                }
+            }
+            RequiredMoves::Unknown => {
+                ui.label("Estimated time: unknown, there is no known optimal solution for these settings.");
+            }
+            RequiredMoves::Overflow => {
+                for (label, _) in TIME_ESTIMATIONS {
+                    ui.label(format!("Estimated time for {}: ∞", label));
+                }
+                ui.colored_label(Color32::YELLOW, "Warning: The required moves are too many to count.");
             }
             RequiredMoves::Count(moves) => {
                 let moves = (moves - 1) as f64;
@@ -559,62 +1748,185 @@ impl HanoiApp {
         .show(ctx, |ui| {
             let max_disks = if self.extra_mode { MAX_DISKS } else { MAX_DISKS_NORMAL };
             let max_poles = if self.extra_mode { MAX_POLES } else { MAX_POLES_NORMAL };
-            ui.add(Slider::new(&mut self.replays_filter.disks, 1..=max_disks).text("Disks"));
-            {
-                let resp = ui.add(Slider::new(&mut self.replays_filter.poles, 2..=max_poles).text("Poles"));
-                if resp.changed {
-                    self.replays_filter.start_pole = self.replays_filter.start_pole.min(self.replays_filter.poles);
-                }
-                resp
-            };
-            ui.add(Slider::new(&mut self.replays_filter.start_pole, 1..=self.replays_filter.poles).text("Start pole"));
+            let mut headers = self.highscores
+                .iter()
+                .filter(|(_, scores)| !scores.is_empty())
+                .map(|(header, _)| *header)
+                .collect::<Vec<Header>>();
+            headers.sort_by_key(Header::sort_key);
+            ComboBox::from_label("Recorded settings")
+                .selected_text(self.replays_filter.to_string())
+                .show_ui(ui, |ui| {
+                    for header in headers {
+                        ui.selectable_value(&mut self.replays_filter, header, header.to_string());
+                    }
+                });
+
+            let filter = &mut self.replays_filter;
+            ui.horizontal(|ui| {
+                ui.label("Disks");
+                integer_input(ui, &mut filter.disks, 1..=max_disks);
+                ui.label("Poles");
+                integer_input(ui, &mut filter.poles, 2..=max_poles);
+                filter.start_pole = filter.start_pole.min(filter.poles);
+                filter.end_pole = filter.end_pole.map(|end_pole| end_pole.min(filter.poles));
+                ui.label("Start pole");
+                integer_input(ui, &mut filter.start_pole, 1..=filter.poles);
+            });
             
+            ui.checkbox(&mut self.replays_filter.spread_goal, "Spread goal");
+            ui.checkbox(&mut self.replays_filter.merge_start, "Merge start");
             let mut any_pole = self.replays_filter.end_pole.is_none();
-            ui.checkbox(&mut any_pole, "Any end pole");
-            if any_pole {
+            if self.replays_filter.spread_goal {
                 self.replays_filter.end_pole = None;
             } else {
-                let end_pole = self.replays_filter.end_pole.get_or_insert(1);
-                ui.add(Slider::new(end_pole, 1..=self.replays_filter.poles).text("End pole"));
-            };
+                ui.checkbox(&mut any_pole, "Any end pole");
+                if any_pole {
+                    self.replays_filter.end_pole = None;
+                } else {
+                    let poles = self.replays_filter.poles;
+                    ui.horizontal(|ui| {
+                        ui.label("End pole");
+                        let start_pole = self.replays_filter.start_pole;
+                        integer_input(ui, self.replays_filter.end_pole.get_or_insert(HanoiGame::default_end_pole(start_pole, poles)), 1..=poles);
+                        if self.replays_filter.end_pole == Some(start_pole) {
+                            ui.colored_label(Color32::YELLOW, "same as the start pole");
+                        }
+                    });
+                };
+            }
 
             ui.checkbox(&mut self.replays_filter.illegal_moves, "Illegal moves");
+            if self.replays_filter.illegal_moves {
+                set_enum_setting(ui, &mut self.replays_filter.illegal_counting)
+                    .on_hover_text(ILLEGAL_COUNTING_HELP);
+            } else {
+                self.replays_filter.illegal_counting = Default::default();
+            }
+            ui.checkbox(&mut self.replays_filter.multi_move, "Multi-moves");
+            if self.replays_filter.multi_move {
+                set_enum_setting(ui, &mut self.replays_filter.multi_move_counting)
+                    .on_hover_text(MULTI_MOVE_COUNTING_HELP);
+            } else {
+                self.replays_filter.multi_move_counting = Default::default();
+            }
             ui.checkbox(&mut self.replays_filter.blindfold, "Blindfold");
+            ui.checkbox(&mut self.replays_filter.outline, "Outlines only");
+
+            if self.highscores.get(&self.replays_filter).is_none_or(Vec::is_empty) {
+                ui.horizontal(|ui| {
+                    ui.label("No data for these settings.");
+                    if let Some(nearest) = self.nearest_header(self.replays_filter) {
+                        if ui.button("Go to the nearest recorded settings").clicked() {
+                            self.replays_filter = nearest;
+                        }
+                    }
+                });
+            }
 
             ui.separator();
 
             self.draw_highscores_graph(ui);
+            self.draw_header_stats(ui);
 
             ui.separator();
 
             self.draw_highscores_table(ui);
+
+            ui.separator();
+
+            self.draw_replay_code_import(ui);
+            self.draw_runs_file(ui);
+
+            ui.separator();
+
+            self.draw_statistics(ui);
         });
 
         self.replays_window = self.replays_window && replays_window;
     } 
 
+    pub fn draw_statistics(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        ui.collapsing("Statistics", |ui| {
+            let stats = self.lifetime_stats;
+            ui.label(format!("Games finished: {}", stats.games));
+            ui.label(format!("Total moves: {}", stats.moves));
+            ui.label(format!("Total time: {}", pretty_duration(&stats.time, None)));
+
+            ui.horizontal(|ui| {
+                if ui.button("Reset statistics")
+                    .on_hover_text("Double click to reset the totals above, your high scores and replays are kept")
+                    .double_clicked()
+                {
+                    self.lifetime_stats = Default::default();
+                }
+                if ui.button("Clear high scores")
+                    .on_hover_text("Double click to delete every high score and replay, the totals above are kept")
+                    .double_clicked()
+                {
+                    self.highscores.clear();
+                }
+            });
+        });
+    }
+
     pub fn draw_highscores_graph(&mut self, ui: &mut Ui) {
         puffin::profile_function!();
 
+        ui.add(Slider::new(&mut self.graph_height, MIN_GRAPH_HEIGHT..=MAX_GRAPH_HEIGHT).text("Graph height"));
+
+        let times = self.highscores
+            .get(&self.replays_filter)
+            .map(|scores| scores.iter().map(|score| score.time.as_secs_f64()).collect::<Vec<f64>>())
+            .unwrap_or_default();
+        let stats = self.header_stats(&self.replays_filter);
+
         egui_plot::Plot::new("highscores_plot")
-            .height(128.0)
+            .height(self.graph_height as f32)
             .show_axes(false)
             .data_aspect(1.0)
-            .show(ui, |plot_ui| plot_ui.bar_chart(BarChart::new(
-                match self.highscores.get(&self.replays_filter) {
-                    Some(scores) =>
-                        scores
-                            .iter()
-                            .enumerate()
-                            .map(|(i, score)| Bar::new((i + 1) as f64, score.time.as_secs_f64()))
-                            .collect(),
-                    None => vec![],
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(
+                    times
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &time)| Bar::new((i + 1) as f64, time))
+                        .collect(),
+                ));
+                if let Some(stats) = stats {
+                    let (best, median) = (stats.best.as_secs_f64(), stats.median.as_secs_f64());
+                    plot_ui.hline(HLine::new(best).color(HIGHSCORE_COLOR).name(format!("Best {best:.3}s")));
+                    plot_ui.hline(HLine::new(median).style(LineStyle::dashed_loose()).name(format!("Median {median:.3}s")));
                 }
-            )));
+            });
+    }
+
+    pub fn draw_header_stats(&self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        ui.collapsing("Header statistics", |ui| {
+            let Some(stats) = self.header_stats(&self.replays_filter) else {
+                ui.label("No data");
+                return
+            };
+            ui.label(format!("Runs: {}", stats.runs));
+            ui.label(format!("Best: {:.3} seconds", stats.best.as_secs_f64()));
+            ui.label(format!("Mean: {:.3} seconds", stats.mean.as_secs_f64()));
+            ui.label(format!("Median: {:.3} seconds", stats.median.as_secs_f64()));
+            ui.label(format!("Standard deviation: {:.3} seconds", stats.std_dev.as_secs_f64()));
+            ui.label(format!("Average moves: {:.1}", stats.mean_moves));
+        });
     }
 
     pub fn draw_highscores_table(&mut self, ui: &mut Ui) {
         puffin::profile_function!();
+
+        let mut replay = None;
+        let mut delete = None;
+        let exporting = FRAME_EXPORT_JOB.lock().is_some();
         
         match self.highscores.get(&self.replays_filter) {
             Some(games) if !games.is_empty() => {
@@ -625,30 +1937,75 @@ impl HanoiApp {
                 .column(col_def)
                 .column(col_def)
                 .column(col_def)
+                .column(col_def)
+                .column(col_def)
+                .column(col_def)
+                .column(col_def)
                 .header(30.0, |mut header| {
                     header.col(|ui| { ui.heading("Time"); });
                     header.col(|ui| { ui.heading("Moves"); });
                     header.col(|ui| { ui.heading("Date"); });
                     header.col(|ui| { ui.heading("Replay"); });
+                    header.col(|ui| { ui.heading("File"); });
+                    header.col(|ui| { ui.heading("Code"); });
+                    header.col(|ui| { ui.heading("Frames"); });
+                    header.col(|ui| { ui.heading("Delete"); });
                 })
                 .body(|body| {
                     body.rows(20.0, games.len(), |mut row| {
                         let index = row.index();
                         let game = &games[index];
                         row.col(|ui| { ui.label(format!("{:.3?}s", game.time.as_secs_f64())); });
-                        row.col(|ui| { ui.label(format!("{} moves", game.moves.len())); });
+                        row.col(|ui| {
+                            if game.truncated {
+                                ui.label(format!("≥{} moves", game.moves.len()))
+                                    .on_hover_text("The move history was truncated");
+                            } else {
+                                ui.label(format!("{} moves", game.moves.len()));
+                            }
+                        });
                         row.col(|ui| { ui.label(game.date.format("%Y/%m/%d %H:%M:%S").to_string()); });
                         row.col(|ui| {
-                            if ui.button("Replay").clicked() {
-                                self.player = PlayerKind::Replay(game.clone(), 0);
-                                self.moves = 0;
-                                self.hanoi.disks_count = self.replays_filter.disks;
-                                self.hanoi.poles_count = self.replays_filter.poles;
-                                self.hanoi.start_pole = self.replays_filter.start_pole;
-                                self.hanoi.end_pole = self.replays_filter.end_pole;
-                                self.hanoi.illegal_moves = self.replays_filter.illegal_moves;
-                                self.hanoi.reset();
-                                self.state = GameState::Playing(Instant::now());
+                            let replay_button = ui.add_enabled(!game.truncated, Button::new("Replay"))
+                                .on_disabled_hover_text("This replay was truncated and can't be played back");
+                            if replay_button.clicked() {
+                                replay = Some(game.clone());
+                            }
+                        });
+                        row.col(|ui| {
+                            if ui.add_enabled(!game.truncated, Button::new("Export"))
+                                .on_hover_text("Save this run and its settings to a file, to share it for verification")
+                                .clicked()
+                            {
+                                let dialog = rfd::FileDialog::new()
+                                    .add_filter("Replay", &[REPLAY_EXTENSION])
+                                    .set_file_name(format!("replay.{REPLAY_EXTENSION}"));
+                                if let Some(path) = dialog.save_file() {
+                                    let replay = SharedReplay { header: self.replays_filter, score: game.clone() };
+                                    self.runs_file_result = Some(replay.save(&path).map(|_| format!("Exported the {:.3}s replay", game.time.as_secs_f64())));
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            if ui.add_enabled(!game.truncated, Button::new("Copy code")).clicked() {
+                                let code = SharedReplay { header: self.replays_filter, score: game.clone() }.encode();
+                                ui.output_mut(|output| output.copied_text = code);
+                            }
+                        });
+                        row.col(|ui| {
+                            if ui.add_enabled(!game.truncated && !exporting, Button::new("Export PNGs")).clicked() {
+                                if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+                                    self.frame_export_result = None;
+                                    self.frame_export.spawn(ui.ctx(), directory, self.replays_filter, game.clone(), self.color_theme.clone(), self.poles_position);
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            if ui.button("Delete")
+                                .on_hover_text("Double click to delete this run and its replay")
+                                .double_clicked()
+                            {
+                                delete = Some(index);
                             }
                         });
                     });
@@ -659,35 +2016,360 @@ impl HanoiApp {
                 ui.label("No replay with these settings");
             },
         }
+
+        ui.collapsing("Frame export", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Resolution");
+                ui.add(DragValue::new(&mut self.frame_export.width).range(MIN_FRAME_SIZE..=MAX_FRAME_SIZE).suffix("px"));
+                ui.label("×");
+                ui.add(DragValue::new(&mut self.frame_export.height).range(MIN_FRAME_SIZE..=MAX_FRAME_SIZE).suffix("px"));
+            });
+            ui.checkbox(&mut self.frame_export.timer, "Timer overlay");
+        });
+        {
+            let mut job = FRAME_EXPORT_JOB.lock();
+            if let Some(result) = job.as_ref().and_then(|job| job.finished()) {
+                self.frame_export_result = Some(result);
+                *job = None;
+            }
+            if let Some(job) = job.as_ref() {
+                ui.add(ProgressBar::new(job.written() as f32 / job.total as f32)
+                    .text(format!("Exporting frame {} of {}", job.written(), job.total)));
+            }
+        }
+        match &self.frame_export_result {
+            Some(Ok(frames)) => { ui.label(format!("Exported {frames} frames")); },
+            Some(Err(err)) => { ui.colored_label(Color32::RED, err); },
+            None => {},
+        }
+
+        if let Some(index) = delete {
+            if let Some(scores) = self.highscores.get_mut(&self.replays_filter) {
+                scores.remove(index);
+                if scores.is_empty() {
+                    self.highscores.remove(&self.replays_filter);
+                }
+            }
+        }
+        if let Some(score) = replay {
+            self.load_replay(self.replays_filter, score);
+        }
+    }
+
+    pub fn draw_replay_code_import(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        ui.horizontal(|ui| {
+            ui.label("Replay code");
+            if ui.text_edit_singleline(&mut self.replay_code).changed() {
+                self.decoded_replay = (!self.replay_code.trim().is_empty()).then(|| SharedReplay::decode(&self.replay_code));
+            }
+            if ui.button("Open replay file").on_hover_text("Watch an exported replay, it can then be saved to your runs").clicked() {
+                let dialog = rfd::FileDialog::new().add_filter("Replay", &[REPLAY_EXTENSION]);
+                if let Some(path) = dialog.pick_file() {
+                    let replay = SharedReplay::load(&path);
+                    if let Ok(SharedReplay { header, score }) = &replay {
+                        self.load_replay(*header, score.clone());
+                    }
+                    self.replay_code.clear();
+                    self.decoded_replay = Some(replay);
+                }
+            }
+        });
+
+        match &self.decoded_replay {
+            Some(Ok(replay)) => {
+                let SharedReplay { header, score } = replay.clone();
+                ui.label(format!(
+                    "{:.3?} seconds, {} moves, {} disks, {} poles",
+                    score.time.as_secs_f64(), score.moves.len(), header.disks, header.poles,
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Watch only").clicked() {
+                        self.load_replay(header, score.clone());
+                    }
+                    if ui.button("Import & save").clicked() {
+                        if !self.get_highscores_entry(header).contains(&score) {
+                            self.insert_score(header, score);
+                        }
+                        self.replays_filter = header;
+                        self.replay_code.clear();
+                        self.decoded_replay = None;
+                    }
+                });
+            },
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+            },
+            None => {},
+        }
+    }
+
+    /// Shares the current settings as a code, or applies the settings of a pasted one.
+    /// Saves the runs of the filtered settings to a file, or adds the runs of such a file to its settings.
+    pub fn draw_runs_file(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        let dialog = || rfd::FileDialog::new()
+            .add_filter("Runs", &["json"])
+            .set_file_name("runs.json");
+
+        ui.horizontal(|ui| {
+            let scores = self.highscores.get(&self.replays_filter).filter(|scores| !scores.is_empty());
+            if ui.add_enabled(scores.is_some(), Button::new("Export these runs")).clicked() {
+                if let (Some(scores), Some(path)) = (scores, dialog().save_file()) {
+                    let runs = HeaderRuns { header: self.replays_filter, scores: scores.clone() };
+                    self.runs_file_result = Some(runs.save(&path).map(|_| format!("Exported {} runs", runs.scores.len())));
+                }
+            }
+            if ui.button("Import runs").clicked() {
+                if let Some(path) = dialog().pick_file() {
+                    self.runs_file_result = Some(HeaderRuns::load(&path).map(|runs| {
+                        let header = runs.header;
+                        let added = self.merge_runs(runs);
+                        self.replays_filter = header;
+                        format!("Imported {added} new runs")
+                    }));
+                }
+            }
+        });
+
+        let dialog = || rfd::FileDialog::new()
+            .add_filter("High scores", &["json"])
+            .set_file_name("highscores.json");
+
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!self.highscores.is_empty(), Button::new("Export all high scores"))
+                .on_hover_text("Save the runs of every setting to a file, to carry them to another machine.")
+                .clicked()
+            {
+                if let Some(path) = dialog().save_file() {
+                    let runs = self.highscores.values().map(Vec::len).sum::<usize>();
+                    self.runs_file_result = Some(HeaderRuns::save_all(&self.highscores, &path).map(|_| format!("Exported {runs} runs")));
+                }
+            }
+            if ui.button("Import high scores")
+                .on_hover_text("Add the runs of an exported file, the ones already recorded are skipped.")
+                .clicked()
+            {
+                if let Some(path) = dialog().pick_file() {
+                    self.runs_file_result = Some(HeaderRuns::load_all(&path).map(|runs| {
+                        format!("Imported {} new runs", self.merge_all_runs(runs))
+                    }));
+                }
+            }
+        });
+
+        match &self.runs_file_result {
+            Some(Ok(message)) => { ui.label(message); },
+            Some(Err(err)) => { ui.colored_label(Color32::RED, err); },
+            None => {},
+        }
+    }
+
+    pub fn draw_puzzles(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        ui.add_enabled_ui(self.daily.is_none(), |ui| ui.horizontal(|ui| {
+            let mut changed = ui.checkbox(&mut self.scramble, "Random start")
+                .on_hover_text("Start every game from a new random position with every disk on a larger one.")
+                .changed();
+            ui.add_enabled_ui(self.scramble, |ui| {
+                changed |= ui.checkbox(&mut self.fixed_seed, "Fixed seed")
+                    .on_hover_text("Deal the position of the seed every game, the same seed always gives the same position.")
+                    .changed();
+            });
+            ui.add_enabled_ui(self.scramble && self.fixed_seed, |ui| {
+                changed |= ui.add(DragValue::new(&mut self.scramble_seed).prefix("Seed ")).changed();
+                if ui.button("New seed").clicked() {
+                    self.scramble_seed = fastrand::u64(..);
+                    changed = true;
+                }
+            });
+            if changed {
+                if !self.scramble {
+                    self.hanoi.custom_start = None;
+                }
+                self.soft_reset();
+            }
+        }));
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.puzzle_name);
+            let name = self.puzzle_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), Button::new("Save the board"))
+                .on_hover_text("Save the current position as a puzzle to practice from, even in the middle of a game.")
+                .clicked()
+            {
+                self.save_puzzle(name);
+                self.puzzle_name.clear();
+            }
+        });
+
+        let mut load = None;
+        let mut delete = None;
+        for (i, preset) in self.puzzle_presets.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} ({})", preset.name, preset.header));
+                if ui.button("Load").clicked() {
+                    load = Some(i);
+                }
+                if ui.button("🗑").on_hover_text("Delete this puzzle").clicked() {
+                    delete = Some(i);
+                }
+            });
+        }
+        if let Some(i) = load {
+            if let Err(err) = self.load_puzzle(i) {
+                self.show_toast(err);
+            }
+        }
+        if let Some(i) = delete {
+            self.puzzle_presets.remove(i);
+        }
+
+        if self.hanoi.custom_start.is_some() && ui.button("Back to the tower").on_hover_text("Start from the usual tower again.").clicked() {
+            self.leave_puzzle();
+        }
+    }
+
+    pub fn draw_daily(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        let today = DailyHeader::today();
+        ui.label(format!("Today is {} (seed {}): {} disks on {} poles, from the same random position for everyone.", today.date, today.seed(), today.disks, today.poles));
+        ui.horizontal(|ui| {
+            if ui.button(if self.daily.is_some() { "Restart today's challenge" } else { "Play today's challenge" }).clicked() {
+                self.start_daily();
+            }
+            if self.daily.is_some() && ui.button("Leave the challenge").clicked() {
+                self.leave_daily();
+            }
+        });
+
+        let mut days = self.daily_highscores.iter().filter(|(_, scores)| !scores.is_empty()).collect::<Vec<_>>();
+        days.sort_by(|(a, _), (b, _)| b.cmp(a));
+        for (daily, scores) in days {
+            ui.label(format!("{}: {:.3?} seconds, {} runs", daily.date, scores[0].time.as_secs_f64(), scores.len()));
+        }
+    }
+
+    pub fn draw_config_code(&mut self, ui: &mut Ui) {
+        puffin::profile_function!();
+
+        if ui.button("Copy config code").on_hover_text("Copy a code with these settings, for others to play the same puzzle.").clicked() {
+            let code = self.get_current_header().encode();
+            ui.output_mut(|output| output.copied_text = code);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Paste code");
+            if ui.text_edit_singleline(&mut self.config_code).changed() {
+                self.decoded_config = (!self.config_code.trim().is_empty()).then(|| Header::decode(&self.config_code));
+            }
+        });
+
+        match &self.decoded_config {
+            Some(Ok(header)) => {
+                let header = *header;
+                let fits = self.extra_mode || (header.poles <= MAX_POLES_NORMAL && header.disks <= MAX_DISKS_NORMAL);
+                ui.label(header.to_string());
+                ui.add_enabled_ui(fits && !matches!(self.state, GameState::Playing(_)), |ui| {
+                    if ui.button("Apply").clicked() {
+                        self.apply_header(header);
+                        self.soft_reset();
+                        self.config_code.clear();
+                        self.decoded_config = None;
+                    }
+                });
+                if !fits {
+                    ui.colored_label(Color32::RED, "These settings are larger than the normal limits");
+                }
+            },
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+            },
+            None => {},
+        }
     }
 
     pub fn draw_completed_window(&mut self, ctx: &egui::Context, duration: Duration) {
         puffin::profile_function!();
         
+        let mut completed_window = self.completed_window;
+
         Window::new("🏆 Game complete!")
+        .open(&mut completed_window)
         .collapsible(false)
         .auto_sized()
         .show(ctx, |ui| {
             ui.heading(format!("{duration:.3?}"));
 
-            let required_moves = self.hanoi.required_moves().to_number();
-            if self.moves <= required_moves {
+            let stats = self.completed_stats;
+
+            let required_moves = self.hanoi.required_moves();
+            if stats.optimal && required_moves.is_optimal(self.moves) {
                 ui.label("You had the optimal solution!");
             }
     
-            ui.label(format!(
-                "Average moves per second: {:.2}",
-                self.moves as f64 / duration.as_secs_f64(),
-            ));
-    
-            if self.moves > required_moves {
+            if stats.moves_per_second {
                 ui.label(format!(
-                    "Average optimal moves per second: {:.2}",
-                    required_moves as f64 / duration.as_secs_f64(),
+                    "Average moves per second: {:.2}",
+                    self.moves as f64 / duration.as_secs_f64(),
+                ));
+            }
+    
+            if let RequiredMoves::Count(required_moves) = required_moves {
+                if stats.optimal_moves_per_second && self.moves > required_moves {
+                    ui.label(format!(
+                        "Average optimal moves per second: {:.2}",
+                        required_moves as f64 / duration.as_secs_f64(),
+                    ));
+                }
+            }
+
+            let moves = match &self.player {
+                PlayerKind::Replay(score, _) => &score.moves,
+                _ => &self.hanoi.moves_history,
+            };
+
+            if is_perfectly_consistent(moves) {
+                ui.colored_label(HIGHSCORE_COLOR, "🎼 Perfect consistency!");
+            }
+
+            if stats.consistency {
+                if let Some(consistency) = consistency_score(moves) {
+                    ui.label(format!("Consistency: {consistency:.1}%"));
+                }
+            }
+
+            if stats.efficiency && !self.hanoi.history_truncated {
+                if let Some((efficiency, wasteful)) = move_efficiency(&self.hanoi, moves) {
+                    ui.label(format!("Move efficiency: {efficiency:.1}% ({} wasteful moves)", wasteful.len()));
+                    ui.collapsing("Timeline", |ui| draw_move_timeline(ui, moves, &wasteful));
+                }
+            }
+
+            if stats.inputs && !self.hanoi.history_truncated {
+                for (input, count, average_disk) in input_breakdown(&self.hanoi, moves) {
+                    ui.label(format!("{input:?}: {count} moves, average disk {average_disk:.1}"));
+                }
+            }
+
+            if self.hanoi.history_truncated {
+                ui.colored_label(Color32::YELLOW, format!(
+                    "The move history was truncated, the replay only contains the last {} moves",
+                    self.hanoi.moves_history.len(),
                 ));
             }
 
             let highscores = self.get_highscores_entry(self.get_current_header());
+
+            if stats.rank {
+                if let Some(rank) = highscores.iter().position(|score| score.time == duration) {
+                    ui.label(format!("Rank: #{} of {}", rank + 1, highscores.len()));
+                }
+            }
             
             let highscore = highscores.first()
             .and_then(|first| if first.time == duration {
@@ -696,27 +2378,34 @@ impl HanoiApp {
                 Some(first)
             });
 
-            if let Some(highscore) = highscore {
+            if let Some(highscore) = highscore.filter(|_| stats.best_time) {
                 ui.label(format!("Your best time: {:.3?} seconds", highscore.time.as_secs_f64()));
-                if duration > highscore.time {
+                if !highscore.is_beaten_by(duration) {
                     ui.label(format!("High score difference: +{:.3?} seconds", (duration - highscore.time).as_secs_f64()));
                 } else {
-                    ui.label(RichText::new("New high score!").color(Color32::from_rgb(0xFF, 0xA5, 0x00)));
+                    ui.label(RichText::new("New high score!").color(HIGHSCORE_COLOR));
                     ui.label(format!("Difference: -{:.3?} seconds", (highscore.time - duration).as_secs_f64()));
                 }
             }
+
+            if let Some(&goal) = self.goal_times.get(&self.get_current_header()) {
+                if duration <= goal {
+                    ui.colored_label(Color32::GREEN, format!("Goal met by {:.3} seconds!", (goal - duration).as_secs_f64()));
+                } else {
+                    ui.colored_label(Color32::RED, format!("Goal missed by {:.3} seconds", (duration - goal).as_secs_f64()));
+                }
+            }
+
+            ui.collapsing("Share preview", |ui| {
+                ui.label(self.share_text(duration));
+            });
         });
+
+        self.completed_window = completed_window;
     }
 
     fn share_button(&self, ui: &mut Ui) {
         if let GameState::Finished(time) = self.state {
-            let required_moves = self.hanoi.required_moves().to_number();
-            let is_optimal = self.moves <= required_moves; 
-
-            let time_f64 = time.as_secs_f64();
-
-            static LAST_SHARE: Lazy<Arc<Mutex<Instant>>> = Lazy::new(|| Arc::new(Mutex::new(Instant::now() - SHARE_BUTTON_DURATION)));
-
             let button_text = if LAST_SHARE.lock().elapsed() < SHARE_BUTTON_DURATION {
                 "Copied to clipboard!"
             } else {
@@ -724,53 +2413,124 @@ impl HanoiApp {
             };
 
             if ui.button(button_text).clicked() {
-                let time_string = format!("{:.3?}", time_f64);
-                let tower_share = draw_share_tower(self.color_theme, self.poles_position);
-
-                let share_text = formatdoc!(
-                    "
-                        {tower_share}
-                        {APP_NAME} Result:
-                        🥞 {} disks
-                        ⏱️ {} seconds
-                        🎲 {}/{} moves
-                        🏎️ {:.2?}{} moves/second
-                        {}
-                    ",
-                    self.hanoi.disks_count,
-                    time_string,
-                    self.moves, required_moves,
-                    required_moves as f64 / time_f64, if is_optimal { "" } else { " optimal" }, // yes this is intended
-                    [
-                        (!is_optimal).then_some(format!("🚗 {:.2?} moves/second", self.moves as f64 / time_f64).as_str()),
-                        (self.hanoi.poles_count != 3).then_some(format!("🗼 {} poles", self.hanoi.poles_count).as_str()),
-                        is_optimal.then_some("💯 Optimal solution"),
-                        self.blindfold.then_some("😎 Blindfolded"),
-                        self.hanoi.illegal_moves.then_some("👮 Illegal moves"),
-                        (self.quick_keys.len() != self.hanoi.poles_count * (self.hanoi.poles_count - 1))
-                            .then_some(format!("⌨️ {} quick keys", self.quick_keys.len()).as_str()),
-                        matches!(self.player, PlayerKind::Replay(_, _)).then_some("🎥 Replay"),
-                        time_string.contains("69").then_some("🤣 0 bitches"),
-                        time_string.contains("247").then_some("😱 #247"),
-                    ]
-                        .into_iter()
-                        .flatten()
-                        .fold(String::new(), |mut a, b| {
-                            a += b;
-                            a += "\n";
-                            a
-                        })
-                        .trim_end()
-                );
-
-                ui.output_mut(|output| {
-                    output.copied_text = share_text.to_string();
-                });
-    
-                *LAST_SHARE.lock() = Instant::now();
+                self.copy_share_text(ui.ctx(), time);
             }
         }
-    }    
+    }
+
+    /// Copies the share text to the clipboard, unless it was copied less than `SHARE_BUTTON_DURATION` ago.
+    pub fn copy_share_text(&self, ctx: &egui::Context, time: Duration) {
+        let mut last_share = LAST_SHARE.lock();
+        if last_share.elapsed() < SHARE_BUTTON_DURATION { return }
+
+        let share_text = self.share_text(time);
+        ctx.output_mut(|output| {
+            output.copied_text = share_text;
+        });
+        *last_share = Instant::now();
+    }
+
+    pub fn share_text(&self, time: Duration) -> String {
+        let required_moves = self.hanoi.required_moves();
+        let is_optimal = required_moves.is_optimal(self.moves);
+        // without a known optimum, the speed line falls back to the player's own moves
+        let optimal_moves = match required_moves {
+            RequiredMoves::Count(moves) => moves,
+            _ => self.moves,
+        };
+
+        let time_f64 = time.as_secs_f64();
+
+        let time_string = self.share_time_format.format(time);
+        let time_digits = time_string.chars().filter(char::is_ascii_digit).collect::<String>();
+        let tower_share = draw_share_tower(&self.color_theme, self.poles_position);
+        let moves = match &self.player {
+            PlayerKind::Replay(score, _) => &score.moves,
+            _ => &self.hanoi.moves_history,
+        };
+
+        formatdoc!(
+            "
+                {tower_share}
+                {APP_NAME} Result:
+                🥞 {} disks
+                ⏱️ {}
+                🎲 {}/{} moves
+                🏎️ {:.2?}{} moves/second
+                {}
+            ",
+            self.hanoi.disks_count,
+            time_string,
+            self.moves, required_moves,
+            optimal_moves as f64 / time_f64, if self.moves <= optimal_moves { "" } else { " optimal" }, // yes this is intended
+            [
+                (self.moves > optimal_moves).then_some(format!("🚗 {:.2?} moves/second", self.moves as f64 / time_f64).as_str()),
+                (self.hanoi.poles_count != 3).then_some(format!("🗼 {} poles", self.hanoi.poles_count).as_str()),
+                is_optimal.then_some("💯 Optimal solution"),
+                is_perfectly_consistent(moves).then_some("🎼 Perfect consistency"),
+                self.blindfold.then_some("😎 Blindfolded"),
+                self.outline.then_some("✏️ Outlines only"),
+                self.hanoi.illegal_moves.then_some("👮 Illegal moves"),
+                self.hanoi.spread_goal.then_some("🌟 Spread goal"),
+                self.hanoi.merge_start.then_some("🔀 Merge start"),
+                self.hanoi.multi_move.then_some("📦 Multi-moves"),
+                (self.quick_keys.len() != self.hanoi.poles_count * (self.hanoi.poles_count - 1))
+                    .then_some(format!("⌨️ {} quick keys", self.quick_keys.len()).as_str()),
+                matches!(self.player, PlayerKind::Replay(_, _)).then_some("🎥 Replay"),
+                time_digits.contains("69").then_some("🤣 0 bitches"),
+                time_digits.contains("247").then_some("😱 #247"),
+            ]
+                .into_iter()
+                .flatten()
+                .fold(String::new(), |mut a, b| {
+                    a += b;
+                    a += "\n";
+                    a
+                })
+                .trim_end()
+        )
+    }
+}
+
+/// Every move along the time axis, with the `wasteful` ones in red.
+fn draw_move_timeline(ui: &mut Ui, moves: &[Move], wasteful: &[usize]) {
+    puffin::profile_function!();
+
+    let (wasted, optimal): (Vec<_>, Vec<_>) = moves
+        .iter()
+        .enumerate()
+        .map(|(i, Move(time, ..))| (i, [time.as_secs_f64(), 0.0]))
+        .partition(|(i, _)| wasteful.binary_search(i).is_ok());
+
+    egui_plot::Plot::new("move_timeline")
+        .height(48.0)
+        .show_y(false)
+        .show_axes([true, false])
+        .allow_scroll(false)
+        .show(ui, |plot_ui| {
+            plot_ui.points(Points::new(optimal.into_iter().map(|(_, point)| point).collect::<Vec<_>>()).radius(3.0).color(Color32::GREEN));
+            plot_ui.points(Points::new(wasted.into_iter().map(|(_, point)| point).collect::<Vec<_>>()).radius(3.0).color(Color32::RED));
+        });
+}
+
+/// Like `key_input`, but also binds the mouse buttons pressed while focused.
+/// The primary button is left out, since it's needed to click away from the input.
+fn quick_input(ui: &mut Ui, input: &mut QuickInput) -> Response {
+    puffin::profile_function!();
+    let mut from_string = input.to_string();
+    let resp = ui.text_edit_singleline(&mut from_string);
+    if resp.has_focus() {
+        ui.input(|i| for event in &i.events {
+            match event {
+                Event::Key { key, .. } => *input = QuickInput::Key(*key),
+                Event::PointerButton { button, pressed: true, .. } if *button != PointerButton::Primary => {
+                    *input = QuickInput::Pointer(*button);
+                },
+                _ => {},
+            }
+        })
+    }
+    resp
 }
 
 fn key_input(ui: &mut Ui, key: &mut Key) -> Response {
@@ -787,36 +2547,89 @@ fn key_input(ui: &mut Ui, key: &mut Key) -> Response {
     resp
 }
 
-fn integer_input<T: Numeric>(ui: &mut Ui, input: &mut T, extra_mode: bool) -> Response {
+fn integer_input<T: Numeric>(ui: &mut Ui, input: &mut T, range: RangeInclusive<T>) -> Response {
     puffin::profile_function!();
     let resp = ui.add(
         DragValue::new(input)
             .speed(0.0)
-            .range(0..=(if extra_mode { MAX_DISKS } else { MAX_DISKS_NORMAL }))
+            .range(range)
             .clamp_existing_to_range(true)
     );
     resp
 }
 
-fn set_enum_setting<T>(ui: &mut Ui, selected: &mut T)
+fn optional_color_input(ui: &mut Ui, label: &str, color: &mut Option<Color32>, default: Color32) -> Response {
+    puffin::profile_function!();
+    ui.horizontal(|ui| {
+        let mut custom = color.is_some();
+        let mut resp = ui.checkbox(&mut custom, label);
+        if custom {
+            let color = color.get_or_insert(default);
+            let picker = ui.color_edit_button_srgba(color);
+            resp.changed |= picker.changed;
+        } else {
+            *color = None;
+        }
+        resp
+    }).inner
+}
+
+fn set_enum_setting<T>(ui: &mut Ui, selected: &mut T) -> Response
 where
     T: IntoEnumIterator + PartialEq + Copy + Debug + 'static,
 {
     puffin::profile_function!();
     let type_string = std::any::type_name::<T>();
-    ComboBox::from_label(type_string.split("::").last().unwrap_or(type_string))
+    let mut changed = false;
+    let mut resp = ComboBox::from_label(type_string.split("::").last().unwrap_or(type_string))
         .selected_text(format!("{:?}", selected))
         .show_ui(ui, |ui| {
             for mode in T::iter() {
-                ui.selectable_value(selected, mode, format!("{:?}", mode));
+                changed |= ui.selectable_value(selected, mode, format!("{:?}", mode)).changed();
             }
-        });
+        })
+        .response;
+    resp.changed |= changed;
+    resp
 }
 
-fn input_display_key(ui: &mut Ui, key: Key, highlighted: bool) {
+fn input_display_key(ui: &mut Ui, input: QuickInput, highlighted: bool) {
     puffin::profile_function!();
-    let button = ui.button(format!("{key:?}"));
+    let button = ui.button(input.to_string());
     if highlighted {
         button.highlight();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use eframe::egui::{Context, Pos2, RawInput, Rect, ViewportId, ViewportInfo};
+
+    use crate::HanoiApp;
+
+    use super::{ShareTimeFormat, WindowGeometry};
+
+    #[test]
+    fn the_window_geometry_is_tracked_for_the_save() {
+        let mut app = HanoiApp::default();
+        let mut input = RawInput::default();
+        input.viewports.insert(ViewportId::ROOT, ViewportInfo {
+            outer_rect: Some(Rect::from_min_max(Pos2::new(100.0, 50.0), Pos2::new(900.0, 680.0))),
+            inner_rect: Some(Rect::from_min_max(Pos2::new(101.0, 80.0), Pos2::new(899.0, 679.0))),
+            ..Default::default()
+        });
+        let _ = Context::default().run(input, |ctx| app.track_window(ctx));
+        assert_eq!(app.window, Some(WindowGeometry { x: 100, y: 50, width: 798, height: 599 }));
+    }
+
+    #[test]
+    fn minutes_carry_over_instead_of_showing_60_seconds() {
+        let time = Duration::from_micros(59_999_500);
+        assert_eq!(ShareTimeFormat::Minutes.format(time), "1:00.000");
+        assert_eq!(ShareTimeFormat::Seconds.format(time), "60.000 seconds");
+        assert_eq!(ShareTimeFormat::Minutes.format(Duration::from_millis(61_234)), "1:01.234");
+        assert_eq!(ShareTimeFormat::Minutes.format(Duration::from_micros(59_999_400)), "0:59.999");
+    }
+}
@@ -0,0 +1,151 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+use eframe::egui::{self, Align2, Area, Color32, FontId, Id, Order, RichText};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, HanoiApp};
+
+use super::{DiskShape, HUD_MARGIN, TEXT_COLOR};
+
+/// How many samples the rolling averages are taken over.
+const DIAGNOSTICS_SAMPLES: usize = 60;
+/// How long drawing the poles may take before the player is told how to speed it up, about half of a 60 fps frame.
+const DRAW_BUDGET: Duration = Duration::from_millis(8);
+const DRAW_WARNING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Frame rate and drawing time overlay, to check that the app isn't adding delay.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Diagnostics {
+    pub enabled: bool,
+    /// When the input of the current frame was handed to the app.
+    #[serde(skip)]
+    frame_start: Option<Instant>,
+    #[serde(skip)]
+    frame_times: VecDeque<Duration>,
+    #[serde(skip)]
+    draw_times: VecDeque<Duration>,
+    #[serde(skip)]
+    last_draw_warning: Option<Instant>,
+}
+
+impl Diagnostics {
+    /// Called at the start of every frame, before the input is processed.
+    pub fn start_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.frame_start.replace(now) {
+            push_sample(&mut self.frame_times, now - last);
+        }
+    }
+
+    pub fn record_draw(&mut self, time: Duration) {
+        push_sample(&mut self.draw_times, time);
+    }
+
+    /// The average time drawing the poles takes, when it has been over budget for a while
+    /// and the player wasn't warned about it recently.
+    pub fn draw_over_budget(&mut self) -> Option<Duration> {
+        if self.draw_times.len() < DIAGNOSTICS_SAMPLES { return None }
+        if self.last_draw_warning.is_some_and(|warning| warning.elapsed() < DRAW_WARNING_INTERVAL) { return None }
+        let average = average(&self.draw_times).filter(|&average| average > DRAW_BUDGET)?;
+        self.last_draw_warning = Some(Instant::now());
+        Some(average)
+    }
+
+    pub fn fps(&self) -> Option<f64> {
+        average(&self.frame_times).filter(|time| !time.is_zero()).map(|time| 1.0 / time.as_secs_f64())
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<Duration>, sample: Duration) {
+    if samples.len() >= DIAGNOSTICS_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(sample);
+}
+
+fn average(samples: &VecDeque<Duration>) -> Option<Duration> {
+    (!samples.is_empty()).then(|| samples.iter().sum::<Duration>() / samples.len() as u32)
+}
+
+impl HanoiApp {
+    /// Tells the player which options to turn off when drawing the poles gets too slow,
+    /// once the game is over so that the toast doesn't get in the way of a run.
+    pub fn check_draw_budget(&mut self) {
+        if matches!(self.state, GameState::Playing(_)) { return }
+        let Some(time) = self.diagnostics.draw_over_budget() else { return };
+
+        let options = [
+            (self.disk_number, "disk numbers"),
+            (self.afterimage, "afterimages"),
+            (self.target_preview, "the target preview"),
+            (self.disk_shape != DiskShape::Rectangle, "non-rectangular disks"),
+        ]
+            .into_iter()
+            .filter_map(|(enabled, option)| enabled.then_some(option))
+            .collect::<Vec<_>>();
+        let suggestion = if options.is_empty() { "fewer disks".to_string() } else { options.join(", ") };
+
+        self.show_toast(format!("Drawing takes {:.1}ms per frame, try turning off {suggestion}", time.as_secs_f64() * 1000.0));
+    }
+
+    pub fn draw_diagnostics(&self, ctx: &egui::Context) {
+        if !self.diagnostics.enabled { return }
+
+        puffin::profile_function!();
+
+        let fps = self.diagnostics.fps().map_or("-".to_string(), |fps| format!("{fps:.0}"));
+        let draw = average(&self.diagnostics.draw_times).map_or("-".to_string(), |draw| format!("{:.3}ms", draw.as_secs_f64() * 1000.0));
+
+        Area::new(Id::new("diagnostics"))
+            .anchor(Align2::RIGHT_BOTTOM, -egui::vec2(HUD_MARGIN, HUD_MARGIN))
+            .order(Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(Color32::from_black_alpha(160))
+                    .rounding(4.0)
+                    .inner_margin(HUD_MARGIN)
+                    .show(ui, |ui| {
+                        for line in [format!("{fps} fps"), format!("draw {draw}")] {
+                            ui.label(RichText::new(line).font(FontId::monospace(12.0)).color(TEXT_COLOR));
+                        }
+                    });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{GameState, HanoiApp};
+
+    use super::{average, push_sample, DIAGNOSTICS_SAMPLES, DRAW_BUDGET};
+
+    #[test]
+    fn slow_draws_are_reported_after_the_game() {
+        let mut app = HanoiApp::default();
+        (0..DIAGNOSTICS_SAMPLES).for_each(|_| app.diagnostics.record_draw(DRAW_BUDGET * 2));
+        app.state = GameState::Playing(Instant::now());
+        app.check_draw_budget();
+        assert!(app.toast.is_none(), "runs aren't interrupted");
+
+        app.state = GameState::Finished(Duration::from_secs(1));
+        app.check_draw_budget();
+        assert!(app.toast.is_some());
+    }
+
+    #[test]
+    fn rolling_average_keeps_the_latest_samples() {
+        let mut samples = Default::default();
+        assert_eq!(average(&samples), None);
+        for ms in 0..DIAGNOSTICS_SAMPLES as u64 * 2 {
+            push_sample(&mut samples, Duration::from_millis(ms));
+        }
+        assert_eq!(samples.len(), DIAGNOSTICS_SAMPLES);
+        let oldest = DIAGNOSTICS_SAMPLES as u64;
+        let newest = DIAGNOSTICS_SAMPLES as u64 * 2 - 1;
+        assert_eq!(average(&samples), Some(Duration::from_micros((oldest + newest) * 500)));
+    }
+}
@@ -0,0 +1,220 @@
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicUsize, Ordering}, mpsc::{channel, Receiver}, Arc}, time::Duration};
+
+use eframe::egui::{mutex::Mutex, Color32, Context};
+use image::{Rgba, RgbaImage};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{hanoi::HanoiGame, highscores::{Header, Move, Score}};
+
+use super::{themes::ColorTheme, PolesPosition, DISK_WIDTH_MIN, POLE_COLOR, POLE_WIDTH, TEXT_COLOR};
+
+const BACKGROUND_COLOR: Color32 = Color32::from_gray(27);
+const MAX_FRAME_DISK_HEIGHT: u32 = 24;
+/// Width of a timer digit, as a fraction of the frame height.
+const DIGIT_SCALE: f32 = 0.04;
+/// The most frames an export writes, longer replays would fill the disk with a file per move.
+pub const MAX_EXPORT_FRAMES: usize = 10_000;
+
+/// The export running in the background, at most one at a time.
+pub static FRAME_EXPORT_JOB: Lazy<Mutex<Option<FrameExportJob>>> = Lazy::new(Default::default);
+
+/// An export writing its frames on its own thread.
+pub struct FrameExportJob {
+    pub total: usize,
+    written: Arc<AtomicUsize>,
+    result: Receiver<Result<usize, String>>,
+}
+
+impl FrameExportJob {
+    pub fn written(&self) -> usize {
+        self.written.load(Ordering::Relaxed)
+    }
+    /// What the export ended with, `None` while it's still running.
+    pub fn finished(&self) -> Option<Result<usize, String>> {
+        self.result.try_recv().ok()
+    }
+}
+
+/// How the frames of a replay are exported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrameExport {
+    pub width: u32,
+    pub height: u32,
+    pub timer: bool,
+}
+
+impl Default for FrameExport {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            timer: true,
+        }
+    }
+}
+
+impl FrameExport {
+    /// Writes one PNG for the starting position and one after every move of `score` into `directory`,
+    /// calling `progress` with how many were written so far and returning how many were written in all.
+    pub fn export(&self, directory: &Path, header: Header, score: &Score, color_theme: &ColorTheme, poles_position: PolesPosition, progress: impl Fn(usize)) -> Result<usize, String> {
+        let total = score.moves.len() + 1;
+        if total > MAX_EXPORT_FRAMES {
+            return Err(format!("The replay has {total} frames, at most {MAX_EXPORT_FRAMES} can be exported"))
+        }
+        let mut game = header.game();
+        let digits = score.moves.len().to_string().len();
+
+        let write_frame = |index: usize, time: Duration, game: &HanoiGame| {
+            let file = directory.join(format!("{index:0digits$}_{:.3}s.png", time.as_secs_f64()));
+            self.render(game, time, color_theme, poles_position)
+                .save(&file)
+                .map_err(|err| format!("Unable to write {}: {err}", file.display()))?;
+            progress(index + 1);
+            Ok::<_, String>(())
+        };
+
+        write_frame(0, Duration::ZERO, &game)?;
+        for (i, &Move(time, from, to, _)) in score.moves.iter().enumerate() {
+            game.shift(from, to);
+            write_frame(i + 1, time, &game)?;
+        }
+
+        Ok(total)
+    }
+
+    /// Starts exporting on a background thread, unless an export is already running.
+    pub fn spawn(&self, ctx: &Context, directory: PathBuf, header: Header, score: Score, color_theme: ColorTheme, poles_position: PolesPosition) {
+        let mut job = FRAME_EXPORT_JOB.lock();
+        if job.is_some() { return }
+
+        let (sender, result) = channel();
+        let written = Arc::new(AtomicUsize::new(0));
+        *job = Some(FrameExportJob { total: score.moves.len() + 1, written: written.clone(), result });
+
+        let (export, ctx) = (*self, ctx.clone());
+        std::thread::spawn(move || {
+            let progress = |frames| {
+                written.store(frames, Ordering::Relaxed);
+                ctx.request_repaint();
+            };
+            let _ = sender.send(export.export(&directory, header, &score, &color_theme, poles_position, progress));
+            ctx.request_repaint();
+        });
+    }
+
+    fn render(&self, game: &HanoiGame, time: Duration, color_theme: &ColorTheme, poles_position: PolesPosition) -> RgbaImage {
+        let (width, height) = (self.width.max(1), self.height.max(1));
+        let mut image = RgbaImage::from_pixel(width, height, rgba(BACKGROUND_COLOR));
+
+        let column_width = width as f32 / game.poles_count as f32;
+        let disk_height = (height / (game.disks_count as u32 + 2)).clamp(1, MAX_FRAME_DISK_HEIGHT) as f32;
+        let width_step = (column_width - DISK_WIDTH_MIN) / game.disks_count as f32;
+        // the bottom of the tower, growing up, or the top of it, growing down
+        let (base, direction) = match poles_position {
+            PolesPosition::Bottom => (height as f32, -1.0),
+            PolesPosition::Top => (0.0, 1.0),
+        };
+
+        for (i, pole) in game.poles[..game.poles_count].iter().enumerate() {
+            let center = column_width * (i as f32 + 0.5);
+            let pole_length = disk_height * (game.disks_count + 1) as f32;
+            fill_rect(&mut image, center - POLE_WIDTH / 2.0, base.min(base + direction * pole_length), POLE_WIDTH, pole_length, POLE_COLOR);
+
+            for (j, &disk_number) in pole.iter().enumerate() {
+                let disk_width = DISK_WIDTH_MIN + disk_number as f32 * width_step;
+                let top = base + direction * disk_height * (j as f32 + if direction < 0.0 { 1.0 } else { 0.0 });
+                fill_rect(&mut image, center - disk_width / 2.0, top, disk_width, disk_height, color_theme.to_color(disk_number, game.disks_count));
+            }
+        }
+
+        if self.timer {
+            draw_timer(&mut image, time);
+        }
+
+        image
+    }
+}
+
+fn rgba(color: Color32) -> Rgba<u8> {
+    Rgba(color.to_array())
+}
+
+fn fill_rect(image: &mut RgbaImage, x: f32, y: f32, width: f32, height: f32, color: Color32) {
+    let (x0, y0) = (x.max(0.0) as u32, y.max(0.0) as u32);
+    let x1 = ((x + width).max(0.0) as u32).min(image.width());
+    let y1 = ((y + height).max(0.0) as u32).min(image.height());
+    for py in y0..y1 {
+        for px in x0..x1 {
+            image.put_pixel(px, py, rgba(color));
+        }
+    }
+}
+
+/// Writes the time in the top left corner with seven-segment digits, so that no font is needed.
+fn draw_timer(image: &mut RgbaImage, time: Duration) {
+    // segments in the order: top, top right, bottom right, bottom, bottom left, top left, middle
+    const DIGITS: [u8; 10] = [0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101, 0b1111101, 0b0000111, 0b1111111, 0b1101111];
+
+    let digit_width = (image.height() as f32 * DIGIT_SCALE).max(4.0);
+    let stroke = (digit_width / 5.0).max(1.0);
+    let segment = digit_width;
+    let mut x = digit_width;
+    let y = digit_width;
+
+    for c in format!("{:.3}", time.as_secs_f64()).chars() {
+        if c == '.' {
+            fill_rect(image, x, y + segment * 2.0 - stroke, stroke, stroke, TEXT_COLOR);
+            x += stroke * 3.0;
+            continue;
+        }
+        let Some(segments) = c.to_digit(10).map(|digit| DIGITS[digit as usize]) else { continue };
+        let rects = [
+            (x, y, digit_width, stroke),
+            (x + digit_width - stroke, y, stroke, segment),
+            (x + digit_width - stroke, y + segment, stroke, segment),
+            (x, y + segment * 2.0 - stroke, digit_width, stroke),
+            (x, y + segment, stroke, segment),
+            (x, y, stroke, segment),
+            (x, y + segment - stroke / 2.0, digit_width, stroke),
+        ];
+        for (i, &(rx, ry, rw, rh)) in rects.iter().enumerate() {
+            if segments & (1 << i) != 0 {
+                fill_rect(image, rx, ry, rw, rh, TEXT_COLOR);
+            }
+        }
+        x += digit_width + stroke * 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, time::Duration};
+
+    use crate::{display::{themes::ColorTheme, PolesPosition}, highscores::{Header, Move, Score}};
+
+    use super::FrameExport;
+
+    #[test]
+    fn replays_export_a_frame_per_move() {
+        let directory = std::env::temp_dir().join(format!("hanoi_frames_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let header = Header { disks: 2, poles: 3, ..Default::default() };
+        let score = Score {
+            moves: vec![Move(Duration::from_millis(100), 0, 1, None), Move(Duration::from_millis(200), 0, 2, None), Move(Duration::from_millis(300), 1, 2, None)],
+            ..Default::default()
+        };
+        let export = FrameExport { width: 64, height: 48, timer: true };
+        let written = Cell::new(0);
+        let frames = export.export(&directory, header, &score, &ColorTheme::default(), PolesPosition::Bottom, |frames| written.set(frames));
+
+        let files = std::fs::read_dir(&directory).map(|files| files.count());
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(frames, Ok(4));
+        assert_eq!(written.get(), 4);
+        assert_eq!(files.unwrap(), 4);
+        assert!(!directory.exists());
+    }
+}
@@ -1,8 +1,10 @@
+use std::fmt::Display;
+
 use colorgrad::Gradient;
-use eframe::{egui::Color32, epaint::Hsva};
+use eframe::{egui::{vec2, Color32, Context, Rect, Response, Rgba, Sense, Theme, Ui, Visuals}, epaint::Hsva};
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use strum::EnumIter;
+use serde::{de::{self, SeqAccess, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
 
 use super::PolesPosition;
 
@@ -22,7 +24,7 @@ macro_rules! gradients_generator {
     };
 }
 
-#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, EnumIter, IntoStaticStr)]
 pub enum ColorTheme {
     #[default]
     Purple,
@@ -34,10 +36,55 @@ pub enum ColorTheme {
     Peter,
     Eric,
     Steve,
+    /// Colorblind safe hues that also get darker from the smallest disk to the largest one.
+    HighContrast,
+    /// The user's own colors, repeating from the smallest disk.
+    Custom(Vec<Color32>),
+}
+
+// Saves are read with `DefaultOnError`, which buffers values and loses ron's enum variant names,
+// so custom colors are saved as a plain list and the other themes as their name in a string.
+impl Serialize for ColorTheme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ColorTheme::Custom(colors) => colors.iter().map(|color| color.to_array()).collect::<Vec<[u8; 4]>>().serialize(serializer),
+            theme => serializer.serialize_str(theme.name()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorTheme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ThemeVisitor;
+
+        impl<'de> Visitor<'de> for ThemeVisitor {
+            type Value = ColorTheme;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a theme name or a list of colors")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                ColorTheme::iter()
+                    .find(|theme| theme.name() == name)
+                    .ok_or_else(|| E::unknown_variant(name, &[]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut colors = vec![];
+                while let Some([r, g, b, a]) = seq.next_element::<[u8; 4]>()? {
+                    colors.push(Color32::from_rgba_premultiplied(r, g, b, a));
+                }
+                Ok(ColorTheme::Custom(colors))
+            }
+        }
+
+        deserializer.deserialize_any(ThemeVisitor)
+    }
 }
 
 impl ColorTheme {
-    pub fn to_color(self, disk_number: usize, disks_count: usize) -> Color32 {
+    pub fn to_color(&self, disk_number: usize, disks_count: usize) -> Color32 {
         let modulo = |theme: &[Color32]| theme[(disk_number - 1) % theme.len()];
         let spread = |theme: &[Color32]| theme[(disk_number - 1) * theme.len() / disks_count];
         fn gradient(gradient: &impl Gradient, disk_number: usize, disks_count: usize) -> Color32 {
@@ -59,9 +106,17 @@ impl ColorTheme {
             ColorTheme::Peter => spread(THEME_PETER_COLORS),
             ColorTheme::Eric => spread(THEME_ERIC_COLORS),
             ColorTheme::Steve => spread(THEME_STEVE_COLORS),
+            ColorTheme::HighContrast => high_contrast(disk_number, disks_count),
+            ColorTheme::Custom(colors) if colors.is_empty() => modulo(THEME_PURPLE_COLORS),
+            ColorTheme::Custom(colors) => modulo(colors),
         }
     }
-    pub fn to_emojis(self) -> (char, char, char) {
+    /// The color of `disk_number` once the colors are shuffled with `seed`, or the usual one without a seed.
+    pub fn to_shuffled_color(&self, disk_number: usize, disks_count: usize, seed: Option<u64>) -> Color32 {
+        let disk_number = seed.map_or(disk_number, |seed| shuffled_disk(disk_number, disks_count, seed));
+        self.to_color(disk_number, disks_count)
+    }
+    pub fn to_emojis(&self) -> (char, char, char) {
         match self {
             ColorTheme::Purple => ('🟪', '⬜', '🟪'),
             ColorTheme::Rainbow => ('🟩', '🟦', '🟥'),
@@ -72,6 +127,82 @@ impl ColorTheme {
             ColorTheme::Peter => ('🟫', '⬜', '🟩'),
             ColorTheme::Eric => ('🟦', '⬜', '🟥'),
             ColorTheme::Steve => ('🟫', '🟩', '🟦'),
+            ColorTheme::HighContrast => ('🟨', '🟧', '🟦'),
+            // there aren't enough emojis to match any color
+            ColorTheme::Custom(_) => ('⬜', '⬜', '⬜'),
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+    /// A custom theme starting from the colors this one gives to `disks_count` disks.
+    pub fn to_custom(&self, disks_count: usize) -> Self {
+        match self {
+            ColorTheme::Custom(_) => self.clone(),
+            _ => ColorTheme::Custom((1..=disks_count).map(|disk_number| self.to_color(disk_number, disks_count)).collect()),
+        }
+    }
+}
+
+/// Why a pasted palette couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    InvalidColor(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => f.write_str("No colors"),
+            ParseError::InvalidColor(token) => write!(f, "\"{token}\" isn't a color, expected #RRGGBB"),
+        }
+    }
+}
+
+/// Reads colors written as `#RRGGBB` or `RRGGBB`, separated by commas or whitespace.
+pub fn parse_hex_palette(text: &str) -> Result<Vec<Color32>, ParseError> {
+    let parse_color = |token: &str| {
+        let hex = token.strip_prefix('#').unwrap_or(token);
+        let channel = |i: usize| hex.get(i..i + 2).and_then(|channel| u8::from_str_radix(channel, 16).ok());
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color32::from_rgb(r, g, b)),
+            _ => Err(ParseError::InvalidColor(token.to_string())),
+        }
+    };
+    let colors = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(parse_color)
+        .collect::<Result<Vec<Color32>, ParseError>>()?;
+    if colors.is_empty() {
+        return Err(ParseError::Empty)
+    }
+    Ok(colors)
+}
+
+/// User overrides for the egui visuals, applied on top of both the dark and the light theme.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppVisuals {
+    pub accent: Option<Color32>,
+    pub panel_fill: Option<Color32>,
+    pub window_fill: Option<Color32>,
+}
+
+impl AppVisuals {
+    pub fn apply(&self, ctx: &Context) {
+        for (theme, mut visuals) in [(Theme::Dark, Visuals::dark()), (Theme::Light, Visuals::light())] {
+            if let Some(accent) = self.accent {
+                visuals.selection.bg_fill = accent;
+                visuals.hyperlink_color = accent;
+            }
+            if let Some(panel_fill) = self.panel_fill {
+                visuals.panel_fill = panel_fill;
+            }
+            if let Some(window_fill) = self.window_fill {
+                visuals.window_fill = window_fill;
+            }
+            ctx.set_visuals_of(theme, visuals);
         }
     }
 }
@@ -136,6 +267,22 @@ pub const THEME_STEVE_COLORS: &[Color32] = &[
     // Color32::from_rgb(49, 48, 43),
 ];
 
+/// The space between the swatches of a theme preview.
+const SWATCH_GAP: f32 = 2.0;
+
+/// The Okabe-Ito palette without its black, told apart by every common color vision deficiency.
+pub const THEME_HIGH_CONTRAST_COLORS: &[Color32] = &[
+    Color32::from_rgb(230, 159, 0),     // orange
+    Color32::from_rgb(86, 180, 233),    // sky blue
+    Color32::from_rgb(0, 158, 115),     // bluish green
+    Color32::from_rgb(240, 228, 66),    // yellow
+    Color32::from_rgb(0, 114, 178),     // blue
+    Color32::from_rgb(213, 94, 0),      // vermillion
+    Color32::from_rgb(204, 121, 167),   // reddish purple
+];
+/// The relative luminance of the smallest and of the largest disk of the high contrast theme.
+const HIGH_CONTRAST_LUMINANCE: (f32, f32) = (0.85, 0.04);
+
 gradients_generator!{
     const THEME_SPECKY_COLORS/THEME_SPECKY_GRADIENT: CatmullRomGradient = &[
         Color32::from_rgb(255, 43, 254),
@@ -144,7 +291,51 @@ gradients_generator!{
     ];
 }
 
-pub fn draw_share_tower(color_theme: ColorTheme, poles_position: PolesPosition) -> String {
+fn relative_luminance(color: Rgba) -> f32 {
+    0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b()
+}
+
+/// The palette's hues in turn, darkened or lightened along a luminance ramp so that neighbors differ in brightness too.
+fn high_contrast(disk_number: usize, disks_count: usize) -> Color32 {
+    let hue = Rgba::from(THEME_HIGH_CONTRAST_COLORS[(disk_number - 1) % THEME_HIGH_CONTRAST_COLORS.len()]);
+    let progress = if disks_count > 1 { (disk_number - 1) as f32 / (disks_count - 1) as f32 } else { 0.0 };
+    let (smallest, largest) = HIGH_CONTRAST_LUMINANCE;
+    let target = smallest + (largest - smallest) * progress;
+
+    let luminance = relative_luminance(hue);
+    let channel = |channel: f32| if target <= luminance {
+        channel * target / luminance
+    } else {
+        // towards white
+        channel + (1.0 - channel) * (target - luminance) / (1.0 - luminance)
+    };
+    Color32::from(Rgba::from_rgb(channel(hue.r()), channel(hue.g()), channel(hue.b())))
+}
+
+/// Paints a bar with one segment per disk, smallest on the left, in the colors `color_theme` gives them.
+pub fn draw_theme_preview(ui: &mut Ui, color_theme: &ColorTheme, disks_count: usize, height: f32) -> Response {
+    let (response, painter) = ui.allocate_painter(vec2(ui.available_width(), height), Sense::hover());
+    let segment_width = response.rect.width() / disks_count as f32;
+    // the gaps only fit while the swatches are wide enough to tell apart anyway
+    let gap = if segment_width >= SWATCH_GAP * 4.0 { SWATCH_GAP } else { 0.0 };
+    for disk_number in 1..=disks_count {
+        let min = response.rect.min + vec2(segment_width * (disk_number - 1) as f32, 0.0);
+        painter.rect_filled(Rect::from_min_size(min, vec2(segment_width - gap, height)), gap, color_theme.to_color(disk_number, disks_count));
+    }
+
+    let hovered_disk = response.hover_pos()
+        .map(|pos| ((pos.x - response.rect.left()) / segment_width) as usize + 1)
+        .filter(|disk_number| (1..=disks_count).contains(disk_number));
+    match hovered_disk {
+        Some(disk_number) => {
+            let [r, g, b, _] = color_theme.to_color(disk_number, disks_count).to_array();
+            response.on_hover_text(format!("Disk {disk_number} of {disks_count}: #{r:02X}{g:02X}{b:02X}"))
+        },
+        None => response.on_hover_text(format!("{disks_count} disks")),
+    }
+}
+
+pub fn draw_share_tower(color_theme: &ColorTheme, poles_position: PolesPosition) -> String {
     let b0 = '⬛';
     let (b1, b2, b3) = color_theme.to_emojis();
 
@@ -160,3 +351,70 @@ pub fn draw_share_tower(color_theme: ColorTheme, poles_position: PolesPosition)
 
     lines.join("\n")
 }
+
+/// The disk whose color `disk_number` takes, a permutation of the disks that only depends on `seed`.
+fn shuffled_disk(disk_number: usize, disks_count: usize, seed: u64) -> usize {
+    let mut disks = (1..=disks_count).collect::<Vec<usize>>();
+    fastrand::Rng::with_seed(seed).shuffle(&mut disks);
+    disks[disk_number - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::Color32;
+
+    use crate::HanoiApp;
+
+    use crate::hanoi::MAX_DISKS;
+
+    use super::{parse_hex_palette, relative_luminance, shuffled_disk, ColorTheme, ParseError};
+
+    #[test]
+    fn shuffled_colors_are_a_permutation() {
+        let disks_count = 20;
+        let shuffle = |seed| (1..=disks_count).map(|disk| shuffled_disk(disk, disks_count, seed)).collect::<Vec<_>>();
+        for seed in 0..10 {
+            let mut disks = shuffle(seed);
+            assert_eq!(disks, shuffle(seed), "the same seed gives the same colors");
+            disks.sort();
+            assert_eq!(disks, (1..=disks_count).collect::<Vec<_>>());
+        }
+        assert_ne!(shuffle(1), shuffle(2));
+    }
+
+    #[test]
+    fn custom_themes_repeat_and_round_trip() {
+        let colors = vec![Color32::RED, Color32::from_rgb(1, 2, 3)];
+        let theme = ColorTheme::Custom(colors.clone());
+        assert_eq!((1..=5).map(|disk| theme.to_color(disk, 5)).collect::<Vec<_>>(), [colors[0], colors[1], colors[0], colors[1], colors[0]]);
+        assert_eq!(ColorTheme::Custom(vec![]).to_color(1, 5), ColorTheme::Purple.to_color(1, 5));
+        assert_eq!(ColorTheme::Purple.to_custom(3), ColorTheme::Custom((1..=3).map(|disk| ColorTheme::Purple.to_color(disk, 3)).collect()));
+
+        for theme in [ColorTheme::Specky, theme] {
+            let app = HanoiApp { color_theme: theme.clone(), ..Default::default() };
+            let save = ron::to_string(&app).unwrap();
+            assert_eq!(ron::from_str::<HanoiApp>(&save).unwrap().color_theme, theme);
+        }
+    }
+
+    #[test]
+    fn high_contrast_disks_get_darker() {
+        for disks_count in 1..=MAX_DISKS {
+            let luminances = (1..=disks_count)
+                .map(|disk| relative_luminance(ColorTheme::HighContrast.to_color(disk, disks_count).into()))
+                .collect::<Vec<f32>>();
+            assert!(luminances.windows(2).all(|pair| pair[0] > pair[1]), "{disks_count} disks: {luminances:?}");
+        }
+    }
+
+    #[test]
+    fn hex_palettes_parse_with_any_separator() {
+        let colors = vec![Color32::from_rgb(255, 43, 254), Color32::from_rgb(254, 254, 254), Color32::from_rgb(114, 253, 255)];
+        assert_eq!(parse_hex_palette("#FF2BFE, #FEFEFE, #72FDFF"), Ok(colors.clone()));
+        assert_eq!(parse_hex_palette(" ff2bfe\n#fefefe,,72FDFF "), Ok(colors));
+        assert_eq!(parse_hex_palette(" , "), Err(ParseError::Empty));
+        for invalid in ["#FF2BF", "#FF2BFEE", "#GG0000", "red", "#FF2BFE,#ÿÿÿ"] {
+            assert!(matches!(parse_hex_palette(invalid), Err(ParseError::InvalidColor(_))), "{invalid}");
+        }
+    }
+}
@@ -4,13 +4,60 @@ pub const MAX_POLES: usize = 16;
 pub const MAX_DISKS_NORMAL: usize = 30;
 pub const MAX_POLES_NORMAL: usize = 9;
 
+/// The longest optimal solution `next_optimal_move` walks to find where a game without a formula is at.
+const HINT_MAX_MOVES: u128 = 1 << 16;
+/// How many random positions a scramble draws before giving up on finding an unfinished one.
+const SCRAMBLE_TRIES: usize = 1000;
+
 use std::fmt::Display;
 
 use arrayvec::ArrayVec;
 use cached::proc_macro::cached;
 use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+use crate::{highscores::Move, PolesVec};
+
+/// The disks of every pole in play, from the bottom to the top.
+pub type Board = Vec<Vec<usize>>;
+
+/// How moves are counted when illegal moves are enabled.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, Serialize, Deserialize)]
+pub enum IllegalMoveCounting {
+    /// Every move counts as one.
+    #[default]
+    Every,
+    /// Placing a larger disk on a smaller one is free, only moves following the normal rules count.
+    LegalOnly,
+    /// Placing a larger disk on a smaller one counts as two moves.
+    Penalty,
+}
+
+/// How a move of several disks at once is counted when multi-moves are enabled.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter, Serialize, Deserialize)]
+pub enum MultiMoveCounting {
+    /// Moving a group of disks counts as one move.
+    #[default]
+    Single,
+    /// Moving a group of disks counts as one move per disk in it.
+    PerDisk,
+}
 
-use crate::highscores::Move;
+/// A constraint on the moves `shift` allows, a move has to satisfy every active rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
+pub enum MoveRule {
+    /// A disk can only be placed on an empty pole or on a larger disk.
+    SmallerOnLarger,
+}
+
+impl MoveRule {
+    /// Whether `disk` can be placed on `to`, for a group of disks it's the bottom one.
+    pub fn allows(self, game: &HanoiGame, disk: usize, to: usize) -> bool {
+        match self {
+            MoveRule::SmallerOnLarger => game.poles[to].last().is_none_or(|&below| disk < below),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HanoiGame {
@@ -21,8 +68,27 @@ pub struct HanoiGame {
     pub start_pole: usize,
     pub end_pole: Option<usize>,
     pub illegal_moves: bool,
+    #[serde(default)]
+    pub illegal_counting: IllegalMoveCounting,
+    /// Finish with every disk alone on a pole other than the start one, instead of stacked as a tower.
+    #[serde(default)]
+    pub spread_goal: bool,
+    /// Start with the disks split in two stacks, the odd ones on the start pole and the even ones on the next,
+    /// to be merged into a single tower.
+    #[serde(default)]
+    pub merge_start: bool,
+    /// Move the largest group of top disks that fits on the destination in one action, instead of only the top disk.
+    #[serde(default)]
+    pub multi_move: bool,
+    #[serde(default)]
+    pub multi_move_counting: MultiMoveCounting,
+    /// A position to start from instead of the tower, to practice a puzzle.
+    #[serde(skip)]
+    pub custom_start: Option<Board>,
     #[serde(skip)]
     pub moves_history: Vec<Move>,
+    #[serde(skip)]
+    pub history_truncated: bool,
 }
 
 impl HanoiGame {
@@ -35,37 +101,377 @@ impl HanoiGame {
             start_pole: 1,
             end_pole: None,
             illegal_moves: false,
+            illegal_counting: Default::default(),
+            spread_goal: false,
+            merge_start: false,
+            multi_move: false,
+            multi_move_counting: Default::default(),
+            custom_start: None,
 
             moves_history: Vec::with_capacity(1024),
+            history_truncated: false,
         };
         hanoi.reset();
         hanoi
     }
+    /// A game with the same settings, back at its starting position and without history.
+    pub fn restarted(&self) -> Self {
+        let mut game = Self {
+            poles_count: self.poles_count,
+            disks_count: self.disks_count,
+            start_pole: self.start_pole,
+            end_pole: self.end_pole,
+            illegal_moves: self.illegal_moves,
+            illegal_counting: self.illegal_counting,
+            spread_goal: self.spread_goal,
+            merge_start: self.merge_start,
+            multi_move: self.multi_move,
+            multi_move_counting: self.multi_move_counting,
+            custom_start: self.custom_start.clone(),
+            ..Self::new()
+        };
+        game.reset();
+        game
+    }
+    /// The rules enforced by the current settings, each variant switches some of them off or on.
+    pub fn rules(&self) -> impl Iterator<Item = MoveRule> {
+        (!self.illegal_moves).then_some(MoveRule::SmallerOnLarger).into_iter()
+    }
+    /// Whether `pole` is one of the poles in play, the ones past `poles_count` are always empty.
+    pub fn in_play(&self, pole: usize) -> bool {
+        pole < self.poles_count
+    }
     pub fn shift(&mut self, from: usize, to: usize) -> bool {
-        if from == to { return false }
-        if let Some(&from_last) = self.poles[from].last() {
-            if self.illegal_moves || from_last < *self.poles[to].last().unwrap_or(&usize::MAX) {
-                let disk = self.poles[from].pop().unwrap();
-                self.poles[to].push(disk);
-                return true
-            }
+        self.shift_group(from, to, self.group_size(from, to))
+    }
+    /// Moves the top `count` disks of `from` onto `to` at once, keeping their order.
+    /// More than one disk only moves with multi-moves enabled, and only when they are stacked smallest to largest.
+    pub fn shift_group(&mut self, from: usize, to: usize, count: usize) -> bool {
+        if from == to || !self.in_play(from) || !self.in_play(to) { return false }
+        if count == 0 || (count > 1 && !self.multi_move) || count > self.ordered_run(from) { return false }
+        let bottom = self.poles[from][self.poles[from].len() - count];
+        if self.rules().all(|rule| rule.allows(self, bottom, to)) {
+            let split = self.poles[from].len() - count;
+            let group = self.poles[from].drain(split..).collect::<ArrayVec<usize, MAX_DISKS>>();
+            self.poles[to].extend(group);
+            return true
         }
         false
     }
+    /// How many top disks of `from` are stacked smallest to largest, the most that can move as a group.
+    fn ordered_run(&self, from: usize) -> usize {
+        let pole = &self.poles[from];
+        let descending = pole.windows(2).rev().take_while(|pair| pair[0] > pair[1]).count();
+        if pole.is_empty() { 0 } else { descending + 1 }
+    }
+    /// How many disks `shift` moves from `from` to `to`: the largest ordered group that the rules allow on `to`,
+    /// or just the top disk without multi-moves.
+    pub fn group_size(&self, from: usize, to: usize) -> usize {
+        if !self.multi_move || !self.in_play(from) || !self.in_play(to) { return 1 }
+        let pole = &self.poles[from];
+        (1..=self.ordered_run(from))
+            .rev()
+            .find(|&count| self.rules().all(|rule| rule.allows(self, pole[pole.len() - count], to)))
+            .unwrap_or(1)
+    }
+    /// How many disks the move at `index` of the history moved, found by playing the history back.
+    /// `None` when the history doesn't reach back to the start.
+    pub fn history_group_size(&self, index: usize) -> Option<usize> {
+        if !self.multi_move { return Some(1) }
+        if self.history_truncated { return None }
+        let mut game = self.restarted();
+        for &Move(_, from, to, _) in self.moves_history.get(..index)? {
+            game.shift(from, to);
+        }
+        let &Move(_, from, to, _) = self.moves_history.get(index)?;
+        Some(game.group_size(from, to))
+    }
+    /// How much the move `shift` would make from `from` onto `to` adds to the move counter.
+    pub fn move_cost(&self, from: usize, to: usize) -> u128 {
+        self.group_move_cost(from, to, self.group_size(from, to))
+    }
+    /// How much moving the top `count` disks of `from` onto `to` adds to the move counter.
+    pub fn group_move_cost(&self, from: usize, to: usize, count: usize) -> u128 {
+        let cost = if self.illegal_moves {
+            let legal = self.poles[from]
+                .len()
+                .checked_sub(count)
+                .and_then(|bottom| self.poles[from].get(bottom))
+                .is_none_or(|&disk| MoveRule::SmallerOnLarger.allows(self, disk, to));
+            match (legal, self.illegal_counting) {
+                (true, _) | (false, IllegalMoveCounting::Every) => 1,
+                (false, IllegalMoveCounting::LegalOnly) => 0,
+                (false, IllegalMoveCounting::Penalty) => 2,
+            }
+        } else {
+            1
+        };
+        match (self.multi_move, self.multi_move_counting) {
+            (true, MultiMoveCounting::PerDisk) => cost * count as u128,
+            _ => cost,
+        }
+    }
     pub fn reset(&mut self) {
         self.moves_history.clear();
+        self.history_truncated = false;
         self.poles.iter_mut().for_each(|a| a.clear());
 
+        // a puzzle made for other settings doesn't apply anymore
+        if self.custom_start.as_ref().is_some_and(|board| self.validate_board(board).is_err()) {
+            self.custom_start = None;
+        }
+        if let Some(board) = &self.custom_start {
+            for (pole, disks) in board.iter().enumerate() {
+                self.poles[pole].extend(disks.iter().copied());
+            }
+            return
+        }
+
         for i in (1..=self.disks_count).rev() {
-            self.poles[self.start_pole - 1].push(i);
+            self.poles[self.initial_pole(i)].push(i);
+        }
+    }
+    /// Starts the game from a random legal position instead of the tower, always the same one for the same `seed`.
+    /// Positions that are already finished are drawn again.
+    pub fn scramble(&mut self, seed: u64) {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        for _ in 0..SCRAMBLE_TRIES {
+            let mut board = vec![vec![]; self.poles_count];
+            // from the largest disk down, so that every disk lands on larger ones
+            for disk in (1..=self.disks_count).rev() {
+                board[rng.usize(..self.poles_count)].push(disk);
+            }
+            self.custom_start = Some(board);
+            self.reset();
+            if !self.finished() { return }
+        }
+        self.custom_start = None;
+        self.reset();
+    }
+    /// The pole every disk starts on, indexed by disk number.
+    fn initial_disk_poles(&self) -> [usize; MAX_DISKS + 1] {
+        let mut disk_poles = [0; MAX_DISKS + 1];
+        match &self.custom_start {
+            Some(board) => board
+                .iter()
+                .enumerate()
+                .for_each(|(pole, disks)| disks.iter().for_each(|&disk| disk_poles[disk] = pole)),
+            None => (1..=self.disks_count).for_each(|disk| disk_poles[disk] = self.initial_pole(disk)),
+        }
+        disk_poles
+    }
+    /// The current position of the poles in play.
+    pub fn board(&self) -> Board {
+        self.poles[..self.poles_count].iter().map(|pole| pole.to_vec()).collect()
+    }
+    /// Whether `board` is a position of these settings: every disk once, on the poles in play,
+    /// and never on a smaller disk unless illegal moves are allowed.
+    pub fn validate_board(&self, board: &Board) -> Result<(), String> {
+        if board.len() != self.poles_count {
+            return Err(format!("{} poles instead of {}", board.len(), self.poles_count))
+        }
+        let mut disks = board.iter().flatten().copied().collect::<Vec<usize>>();
+        disks.sort_unstable();
+        if !disks.iter().copied().eq(1..=self.disks_count) {
+            return Err(format!("the disks aren't exactly 1 to {}", self.disks_count))
+        }
+        if !self.illegal_moves && board.iter().any(|disks| disks.windows(2).any(|pair| pair[0] < pair[1])) {
+            return Err("a disk is on a smaller one".to_string())
+        }
+        Ok(())
+    }
+    /// The pole `disk_number` starts on.
+    pub fn initial_pole(&self, disk_number: usize) -> usize {
+        if self.merge_start && disk_number.is_multiple_of(2) {
+            self.start_pole % self.poles_count
+        } else {
+            self.start_pole - 1
         }
     }
     pub fn required_moves(&self) -> RequiredMoves {
+        if self.spread_goal {
+            if !self.spread_feasible() { return RequiredMoves::Impossible }
+            // from a single tower every disk moves straight to a pole of its own, once
+            return if self.merge_start || self.custom_start.is_some() { RequiredMoves::Unknown } else { RequiredMoves::Count(self.disks_count as u128) }
+        }
+        if self.custom_start.is_some() {
+            return self.optimal_from(&self.initial_disk_poles()).map_or(RequiredMoves::Unknown, RequiredMoves::Count)
+        }
+        if self.multi_move {
+            if self.merge_start || self.illegal_moves { return RequiredMoves::Unknown }
+            // every disk has to move at least once, and the whole tower moves as one group
+            let trips = if self.end_pole == Some(self.start_pole) { 2 } else { 1 };
+            return RequiredMoves::Count(match self.multi_move_counting {
+                MultiMoveCounting::Single => trips,
+                MultiMoveCounting::PerDisk => trips * self.disks_count as u128,
+            })
+        }
+        if self.merge_start {
+            return self.optimal_from(&self.initial_disk_poles()).map_or(RequiredMoves::Unknown, RequiredMoves::Count)
+        }
         if self.end_pole == Some(self.start_pole) { return RequiredMoves::Count(2) }
-        if self.illegal_moves { return RequiredMoves::Count(2 * self.disks_count as u128 - 1) }
-        frame_stewart(self.disks_count, self.poles_count).into()
+        if self.illegal_moves {
+            // the optimal solution stacks the top disks upside down on a free pole,
+            // which places a larger disk on a smaller one `disks - 2` times
+            let disks = self.disks_count as u128;
+            return RequiredMoves::Count(match (disks, self.illegal_counting) {
+                (1, _) => 1,
+                (_, IllegalMoveCounting::Every) => 2 * disks - 1,
+                (_, IllegalMoveCounting::LegalOnly) => disks + 1,
+                (_, IllegalMoveCounting::Penalty) => 3 * disks - 3,
+            })
+        }
+        frame_stewart(self.disks_count, self.poles_count)
+    }
+    /// Every disk needs a pole of its own, without counting the start pole.
+    /// Multi-moves carry a single starting tower around whole, so it never comes apart.
+    pub fn spread_feasible(&self) -> bool {
+        let single_tower = !self.merge_start && self.custom_start.is_none();
+        self.disks_count < self.poles_count && !(self.multi_move && single_tower && self.disks_count > 1)
+    }
+    /// The fewest moves left to finish from the current position.
+    /// Only known for the classic three poles game, `None` otherwise.
+    pub fn optimal_remaining(&self) -> Option<u128> {
+        self.optimal_from(&self.current_disk_poles())
+    }
+    /// The pole every disk is on right now, indexed by disk number.
+    fn current_disk_poles(&self) -> [usize; MAX_DISKS + 1] {
+        let mut disk_poles = [0; MAX_DISKS + 1];
+        for (pole, disks) in self.poles[..self.poles_count].iter().enumerate() {
+            for &disk in disks {
+                disk_poles[disk] = pole;
+            }
+        }
+        disk_poles
+    }
+    /// The fewest moves to finish when every disk is on the pole at its index in `disk_poles`.
+    fn optimal_from(&self, disk_poles: &[usize]) -> Option<u128> {
+        if self.poles_count != 3 || self.illegal_moves || self.spread_goal || self.multi_move || (self.end_pole == Some(self.start_pole) && !self.merge_start && self.custom_start.is_none()) {
+            return None
+        }
+
+        (0..3)
+            .filter(|&target| self.is_target(target))
+            .map(|target| three_poles_distance(disk_poles, self.disks_count, target))
+            .min()
+    }
+    /// How many disks are already where they finish: stacked from the largest one up on the target pole,
+    /// or on the one furthest along when any pole can be the target.
+    pub fn placed_disks(&self) -> usize {
+        if self.spread_goal {
+            return self.poles[..self.poles_count]
+                .iter()
+                .enumerate()
+                .filter(|&(pole, disks)| pole != self.start_pole - 1 && disks.len() == 1)
+                .count()
+        }
+        (0..self.poles_count)
+            .filter(|&pole| self.is_target(pole))
+            .map(|pole| self.poles[pole].iter().zip((1..=self.disks_count).rev()).take_while(|(disk, placed)| *disk == placed).count())
+            .max()
+            .unwrap_or(0)
+    }
+    /// The first move of `solve`, without computing the others when there's a formula for the position.
+    /// Without one, it's only known along the optimal path from the start, as long as that path is short enough to walk.
+    pub fn next_optimal_move(&self) -> Option<(usize, usize)> {
+        let disk_poles = self.current_disk_poles();
+        if let Some(target) = self.optimal_target(&disk_poles) {
+            return first_three_poles_move(&disk_poles, self.disks_count, target)
+        }
+        if self.finished() || self.required_moves().to_number() > HINT_MAX_MOVES { return None }
+        let mut game = self.restarted();
+        for (from, to) in game.solve()? {
+            if game.poles == self.poles {
+                return Some((from, to))
+            }
+            game.shift(from, to);
+        }
+        None
+    }
+    /// The pole the fewest moves finish on from `disk_poles`, when `optimal_from` knows it.
+    fn optimal_target(&self, disk_poles: &[usize]) -> Option<usize> {
+        self.optimal_from(disk_poles)?;
+        (0..3).filter(|&target| self.is_target(target)).min_by_key(|&target| three_poles_distance(disk_poles, self.disks_count, target))
+    }
+    /// Whether the tower can be finished on `pole`.
+    fn is_target(&self, pole: usize) -> bool {
+        self.end_pole.map_or(pole != self.start_pole - 1, |end_pole| pole == end_pole - 1)
+    }
+    /// The end pole picked when one is first chosen, the pole after the start one so that the puzzle isn't trivial.
+    pub fn default_end_pole(start_pole: usize, poles_count: usize) -> usize {
+        start_pole % poles_count + 1
+    }
+    /// Whether the start and end poles make a game worth playing: both on the board,
+    /// and not the same one unless the tower starts out of place.
+    pub fn is_valid_goal(&self) -> bool {
+        let poles = 1..=self.poles_count;
+        if !poles.contains(&self.start_pole) { return false }
+        if self.spread_goal { return true }
+        match self.end_pole {
+            None => true,
+            Some(end_pole) if end_pole == self.start_pole => self.merge_start || self.custom_start.is_some(),
+            Some(end_pole) => poles.contains(&end_pole),
+        }
+    }
+    pub fn target_pole(&self) -> usize {
+        self.end_pole.map_or(self.start_pole % self.poles_count, |end_pole| end_pole - 1)
+    }
+    /// The moves that finish the game from the current position, without touching it.
+    /// From the starting position every variant with a known solution is solved,
+    /// in the middle of a game only the three poles games that `optimal_remaining` knows are.
+    pub fn solve(&self) -> Option<Vec<(usize, usize)>> {
+        if self.required_moves() == RequiredMoves::Impossible { return None }
+        if self.poles != self.restarted().poles {
+            let mut disk_poles = self.current_disk_poles();
+            let target = self.optimal_target(&disk_poles)?;
+            let mut moves = vec![];
+            solve_three_poles(&mut disk_poles, self.disks_count, target, &mut moves);
+            return Some(moves)
+        }
+
+        let from = self.start_pole - 1;
+        let to = self.target_pole();
+        let poles = (0..self.poles_count).collect::<PolesVec<usize>>();
+        let aux = poles.iter().copied().find(|&pole| pole != from && pole != to);
+        let mut moves = vec![];
+
+        if self.multi_move {
+            // only the plain tower is solved, the whole of it moving as one group
+            if self.merge_start || self.illegal_moves || self.spread_goal || self.custom_start.is_some() { return None }
+            let aux = aux?;
+            if from == to {
+                moves.extend([(from, aux), (aux, from)]);
+            } else {
+                moves.push((from, to));
+            }
+        } else if (self.merge_start || self.custom_start.is_some()) && !self.spread_goal {
+            let mut disk_poles = self.initial_disk_poles();
+            let target = self.optimal_target(&disk_poles)?;
+            solve_three_poles(&mut disk_poles, self.disks_count, target, &mut moves);
+        } else if self.spread_goal {
+            if !self.spread_feasible() || self.custom_start.is_some() { return None }
+            moves.extend(poles.iter().copied().filter(|&pole| pole != from).take(self.disks_count).map(|pole| (from, pole)));
+        } else if from == to {
+            let aux = aux?;
+            moves.extend([(from, aux), (aux, from)]);
+        } else if self.illegal_moves && self.disks_count > 1 {
+            let aux = aux?;
+            let top_disks = self.disks_count - 1;
+            moves.extend(std::iter::repeat_n((from, aux), top_disks));
+            moves.push((from, to));
+            moves.extend(std::iter::repeat_n((aux, to), top_disks));
+        } else {
+            solve_tower(self.disks_count, from, to, &poles, &mut moves)?;
+        }
+
+        Some(moves)
     }
     pub fn finished(&self) -> bool {
+        if self.spread_goal {
+            return self.poles[self.start_pole - 1].is_empty()
+                && self.poles[..self.poles_count].iter().all(|pole| pole.len() <= 1)
+        }
+
         let end = ArrayVec::from_iter((1..=self.disks_count).rev());
 
         if let Some(end_pole) = self.end_pole {
@@ -81,24 +487,128 @@ impl HanoiGame {
     }
 }
 
+/// The largest disk that is out of place has to move once, after everything above it
+/// was stacked on the third pole, and then the smaller tower follows it in 2^(k-1) - 1 moves.
+fn three_poles_distance(disk_poles: &[usize], disk: usize, target: usize) -> u128 {
+    if disk == 0 { return 0 }
+    if disk_poles[disk] == target {
+        three_poles_distance(disk_poles, disk - 1, target)
+    } else {
+        let other = 3 - disk_poles[disk] - target;
+        three_poles_distance(disk_poles, disk - 1, other) + (1 << (disk - 1))
+    }
+}
+
+/// The first of the moves `solve_three_poles` makes.
+fn first_three_poles_move(disk_poles: &[usize], disk: usize, target: usize) -> Option<(usize, usize)> {
+    if disk == 0 { return None }
+    if disk_poles[disk] == target {
+        first_three_poles_move(disk_poles, disk - 1, target)
+    } else {
+        let from = disk_poles[disk];
+        first_three_poles_move(disk_poles, disk - 1, 3 - from - target).or(Some((from, target)))
+    }
+}
+
+/// Moves disks `1..=disk` onto `target` from wherever they are, the moves `three_poles_distance` counts.
+fn solve_three_poles(disk_poles: &mut [usize], disk: usize, target: usize, moves: &mut Vec<(usize, usize)>) {
+    if disk == 0 { return }
+    if disk_poles[disk] == target {
+        solve_three_poles(disk_poles, disk - 1, target, moves);
+    } else {
+        let from = disk_poles[disk];
+        let other = 3 - from - target;
+        solve_three_poles(disk_poles, disk - 1, other, moves);
+        moves.push((from, target));
+        disk_poles[disk] = target;
+        solve_three_poles(disk_poles, disk - 1, target, moves);
+    }
+}
+
 #[cached]
-fn frame_stewart(disks: usize, poles: usize) -> Option<u128> {
+fn frame_stewart(disks: usize, poles: usize) -> RequiredMoves {
     match (disks, poles) {
-        (0, _) => Some(0),
-        (1, p) if p > 1 => Some(1),
-        (d, 3) => Some(2_u128.pow(d as u32) - 1),
-        (d, p) if p > 3 => {
-            let mut min = None;
-            for i in 0..d {
-                if let (Some(first), Some(second)) = (frame_stewart(i, p), frame_stewart(d - i, p - 1)) {
-                    let moves = 2 * first + second;
-                    min = Some(min.map_or(moves, |current| moves.min(current)));
-                }
-            }
-            min
+        (0, _) => RequiredMoves::Count(0),
+        (1, p) if p > 1 => RequiredMoves::Count(1),
+        // 2^d - 1 still fits for d == 128, so shift instead of computing 2^d
+        (d, 3) => match u32::try_from(d) {
+            Ok(d) if d <= u128::BITS => RequiredMoves::Count(u128::MAX >> (u128::BITS - d)),
+            _ => RequiredMoves::Overflow,
+        },
+        (d, p) if p > 3 => (0..d)
+            .map(|i| frame_stewart_partition(d, p, i))
+            .min()
+            .unwrap_or(RequiredMoves::Impossible),
+        _ => RequiredMoves::Impossible,
+    }
+}
+
+/// Moves needed when the top `split` disks are parked on a spare pole
+/// while the rest are moved with one pole less.
+fn frame_stewart_partition(disks: usize, poles: usize, split: usize) -> RequiredMoves {
+    match (frame_stewart(split, poles), frame_stewart(disks - split, poles - 1)) {
+        (RequiredMoves::Count(first), RequiredMoves::Count(second)) => first
+            .checked_mul(2)
+            .and_then(|moves| moves.checked_add(second))
+            .map_or(RequiredMoves::Overflow, RequiredMoves::Count),
+        (RequiredMoves::Impossible, _) | (_, RequiredMoves::Impossible) => RequiredMoves::Impossible,
+        _ => RequiredMoves::Overflow,
+    }
+}
+
+#[cached]
+fn frame_stewart_split(disks: usize, poles: usize) -> Option<usize> {
+    (0..disks)
+        .filter_map(|i| match frame_stewart_partition(disks, poles, i) {
+            RequiredMoves::Count(moves) => Some((moves, i)),
+            _ => None,
+        })
+        .min_by_key(|&(moves, _)| moves)
+        .map(|(_, i)| i)
+}
+
+/// Moves the top `disks` disks from `from` to `to` with the Frame-Stewart algorithm,
+/// using only the given `poles` (which include `from` and `to`).
+/// The recursion runs on a stack of its own, so that it doesn't depend on the size of the call stack.
+fn solve_tower(disks: usize, from: usize, to: usize, poles: &[usize], moves: &mut Vec<(usize, usize)>) -> Option<()> {
+    /// A tower of `disks` going from `from` to `to` over `poles`, `phase` says which of its three parts comes next.
+    struct Frame {
+        disks: usize,
+        from: usize,
+        to: usize,
+        poles: PolesVec<usize>,
+        phase: u8,
+    }
+
+    let mut stack = vec![Frame { disks, from, to, poles: poles.iter().copied().collect(), phase: 0 }];
+    while let Some(frame) = stack.last_mut() {
+        match frame.disks {
+            0 => { stack.pop(); },
+            1 => {
+                moves.push((frame.from, frame.to));
+                stack.pop();
+            },
+            disks => {
+                let split = frame_stewart_split(disks, frame.poles.len())?;
+                let aux = *frame.poles.iter().find(|&&pole| pole != frame.from && pole != frame.to)?;
+                let (from, to, poles, phase) = (frame.from, frame.to, frame.poles.clone(), frame.phase);
+                frame.phase += 1;
+                let next = match phase {
+                    // the top disks go out of the way using every pole
+                    0 => Frame { disks: split, from, to: aux, poles, phase: 0 },
+                    // the bottom ones can't use the pole the top ones are on
+                    1 => Frame { disks: disks - split, from, to, poles: poles.iter().copied().filter(|&pole| pole != aux).collect(), phase: 0 },
+                    2 => Frame { disks: split, from: aux, to, poles, phase: 0 },
+                    _ => {
+                        stack.pop();
+                        continue
+                    },
+                };
+                stack.push(next);
+            },
         }
-        _ => None,
     }
+    Some(())
 }
 
 impl Default for HanoiGame {
@@ -107,34 +617,426 @@ impl Default for HanoiGame {
     }
 }
 
+/// Variants are ordered from best to worst, so the cheapest option is the `min()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RequiredMoves {
-    Impossible,
     Count(u128),
-}
-
-impl<T: Into<u128>> From<Option<T>> for RequiredMoves {
-    fn from(value: Option<T>) -> Self {
-        match value {
-            Some(count) => RequiredMoves::Count(count.into()),
-            None => RequiredMoves::Impossible,
-        }
-    }
+    /// Solvable, but the move count doesn't fit in a `u128`.
+    Overflow,
+    /// Solvable, but there is no formula for the optimal move count.
+    Unknown,
+    Impossible,
 }
 
 impl Display for RequiredMoves {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&match self {
             RequiredMoves::Impossible => "∞".to_string(),
+            RequiredMoves::Overflow => format!(">{}", u128::MAX),
+            RequiredMoves::Unknown => "?".to_string(),
             RequiredMoves::Count(moves) => moves.to_string()
         })
     }
 }
 
 impl RequiredMoves {
-    pub fn to_number(&self) -> u128 {
+    pub fn is_optimal(self, moves: u128) -> bool {
+        matches!(self, Self::Count(required) if moves <= required)
+    }
+    pub fn to_number(self) -> u128 {
         match self {
-            Self::Impossible => u128::MAX,
-            Self::Count(m) => *m,
+            Self::Impossible | Self::Overflow | Self::Unknown => u128::MAX,
+            Self::Count(m) => m,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::{frame_stewart, frame_stewart_split, solve_tower, HanoiGame, IllegalMoveCounting, MoveRule, MultiMoveCounting, RequiredMoves, MAX_POLES};
+
+    fn game(disks_count: usize, poles_count: usize) -> HanoiGame {
+        let mut game = HanoiGame {
+            disks_count,
+            poles_count,
+            ..Default::default()
+        };
+        game.reset();
+        game
+    }
+
+    fn apply_solution(game: &mut HanoiGame) -> usize {
+        let moves = game.solve().expect("the game should be solvable");
+        for &(from, to) in &moves {
+            assert!(game.shift(from, to), "illegal move {from} -> {to}");
+        }
+        moves.len()
+    }
+
+    #[test]
+    fn solver_is_optimal_and_legal() {
+        for poles_count in 3..=6 {
+            for disks_count in 1..=12 {
+                let mut game = game(disks_count, poles_count);
+                let required_moves = game.required_moves().to_number();
+                let moves = apply_solution(&mut game);
+                assert_eq!(moves as u128, required_moves, "{disks_count} disks, {poles_count} poles");
+                assert!(game.finished(), "{disks_count} disks, {poles_count} poles");
+            }
+        }
+    }
+
+    #[test]
+    fn solver_rejects_impossible_games() {
+        assert_eq!(game(2, 2).solve(), None);
+        assert_eq!(game(1, 2).solve().map(|moves| moves.len()), Some(1));
+    }
+
+    #[test]
+    fn solver_handles_illegal_moves() {
+        for counting in IllegalMoveCounting::iter() {
+            for disks_count in 1..=12 {
+                let mut game = game(disks_count, 3);
+                game.illegal_moves = true;
+                game.illegal_counting = counting;
+                let required_moves = game.required_moves().to_number();
+                let cost = game.solve().unwrap().into_iter().map(|(from, to)| {
+                    let cost = game.move_cost(from, to);
+                    assert!(game.shift(from, to));
+                    cost
+                }).sum::<u128>();
+                assert_eq!(cost, required_moves, "{disks_count} disks, {counting:?}");
+                assert!(game.finished());
+            }
+        }
+    }
+
+    #[test]
+    fn smaller_on_larger_rule() {
+        let mut game = game(3, 3);
+        let largest = game.poles[0].remove(0);
+        game.poles[1].push(largest);
+        // pole 1: [2, 1], pole 2: [3], pole 3: []
+        assert!(MoveRule::SmallerOnLarger.allows(&game, 1, 1));
+        assert!(MoveRule::SmallerOnLarger.allows(&game, 1, 2));
+        assert!(!MoveRule::SmallerOnLarger.allows(&game, 3, 0));
+        assert!(MoveRule::SmallerOnLarger.allows(&game, 3, 2));
+    }
+
+    #[test]
+    fn shift_enforces_the_active_rules() {
+        let mut game = game(2, 3);
+        assert_eq!(game.rules().collect::<Vec<_>>(), vec![MoveRule::SmallerOnLarger]);
+        assert!(game.shift(0, 1));
+        assert!(!game.shift(0, 1));
+        assert!(!game.shift(2, 1), "moving from an empty pole");
+        assert!(!game.shift(1, 1), "moving onto the same pole");
+
+        game.illegal_moves = true;
+        assert_eq!(game.rules().count(), 0);
+        assert!(game.shift(0, 1));
+        assert_eq!(game.poles[1].as_slice(), &[1, 2]);
+        assert!(!game.shift(2, 1), "moving from an empty pole");
+        assert!(!game.shift(1, 1), "moving onto the same pole");
+    }
+
+    #[test]
+    fn shift_rejects_poles_out_of_play() {
+        let mut game = game(3, 3);
+        assert!(!game.shift(0, 3));
+        assert!(!game.shift(0, MAX_POLES));
+        assert!(game.poles[3].is_empty());
+
+        game.illegal_moves = true;
+        assert!(!game.shift(0, 3));
+        assert!(!game.shift(3, 0));
+        assert_eq!(game.poles[0].len(), 3);
+    }
+
+    #[test]
+    fn merge_start_splits_and_merges() {
+        for disks_count in 1..=10 {
+            let mut game = game(disks_count, 3);
+            game.merge_start = true;
+            game.reset();
+            assert!(game.poles[0].iter().all(|disk| disk % 2 == 1));
+            assert!(game.poles[1].iter().all(|disk| disk.is_multiple_of(2)));
+            assert!(game.poles[..2].iter().all(|pole| pole.windows(2).all(|pair| pair[0] > pair[1])));
+
+            let required_moves = game.required_moves().to_number();
+            assert_eq!(game.optimal_remaining(), Some(required_moves));
+            assert_eq!(apply_solution(&mut game) as u128, required_moves, "{disks_count} disks");
+            assert!(game.finished());
+        }
+
+        let mut game = game(4, 4);
+        game.merge_start = true;
+        assert_eq!(game.required_moves(), RequiredMoves::Unknown);
+    }
+
+    #[test]
+    fn scrambles_are_legal_and_repeatable() {
+        for seed in 0..50 {
+            let mut scrambled = game(8, 3);
+            scrambled.scramble(seed);
+            let board = scrambled.board();
+            assert_eq!(scrambled.validate_board(&board), Ok(()));
+            assert!(!scrambled.finished(), "seed {seed}");
+            assert_eq!(scrambled.custom_start.as_ref(), Some(&board));
+
+            let mut again = game(8, 3);
+            again.scramble(seed);
+            assert_eq!(again.board(), board, "seed {seed}");
+
+            // the classic formula is for the whole tower, a scramble is solved from where its disks are
+            let moves = scrambled.solve().unwrap();
+            assert_eq!(moves.len() as u128, scrambled.required_moves().to_number());
+            moves.into_iter().for_each(|(from, to)| assert!(scrambled.shift(from, to)));
+            assert!(scrambled.finished());
+        }
+        let boards = (0..10).map(|seed| {
+            let mut scrambled = game(8, 3);
+            scrambled.scramble(seed);
+            scrambled.board()
+        }).collect::<Vec<_>>();
+        assert!(boards.iter().any(|board| board != &boards[0]), "the seed picks the position");
+    }
+
+    #[test]
+    fn degenerate_goals_are_invalid() {
+        let mut game = HanoiGame { poles_count: 5, start_pole: 2, end_pole: Some(2), ..Default::default() };
+        assert!(!game.is_valid_goal(), "the tower starts finished");
+        game.merge_start = true;
+        assert!(game.is_valid_goal(), "the merged tower still has to be built");
+
+        let mut game = HanoiGame { poles_count: 5, start_pole: 1, end_pole: Some(5), ..Default::default() };
+        assert!(game.is_valid_goal());
+        game.poles_count = 3;
+        assert!(!game.is_valid_goal(), "the end pole is gone");
+        game.end_pole = None;
+        assert!(game.is_valid_goal());
+        game.start_pole = 4;
+        assert!(!game.is_valid_goal(), "the start pole is gone");
+    }
+
+    #[test]
+    fn default_end_pole_avoids_the_start_pole() {
+        for poles_count in 2..=MAX_POLES {
+            for start_pole in 1..=poles_count {
+                let end_pole = HanoiGame::default_end_pole(start_pole, poles_count);
+                assert_ne!(end_pole, start_pole);
+                assert!((1..=poles_count).contains(&end_pole), "{start_pole} of {poles_count}");
+
+                let game = HanoiGame { start_pole, poles_count, end_pole: Some(end_pole), ..Default::default() };
+                assert_eq!(game.target_pole(), HanoiGame { end_pole: None, ..game.clone() }.target_pole());
+            }
+        }
+        assert_eq!(HanoiGame::default_end_pole(1, 3), 2);
+        assert_eq!(HanoiGame::default_end_pole(3, 3), 1);
+    }
+
+    #[test]
+    fn required_moves_at_the_boundary() {
+        assert_eq!(game(64, 3).required_moves(), RequiredMoves::Count(u64::MAX as u128));
+        assert_eq!(frame_stewart(128, 3), RequiredMoves::Count(u128::MAX));
+        assert_eq!(frame_stewart(129, 3), RequiredMoves::Overflow);
+        assert_eq!(frame_stewart(usize::MAX, 3), RequiredMoves::Overflow);
+        assert!(matches!(frame_stewart(200, 4), RequiredMoves::Count(_)));
+        assert_eq!(frame_stewart(2, 2), RequiredMoves::Impossible);
+    }
+
+    #[test]
+    fn spread_goal_needs_a_pole_per_disk() {
+        for poles_count in 2..=6 {
+            for disks_count in 1..=6 {
+                let mut game = game(disks_count, poles_count);
+                game.spread_goal = true;
+                if disks_count < poles_count {
+                    assert_eq!(game.required_moves(), RequiredMoves::Count(disks_count as u128));
+                    assert_eq!(apply_solution(&mut game), disks_count);
+                    assert!(game.finished());
+                } else {
+                    assert_eq!(game.required_moves(), RequiredMoves::Impossible);
+                    assert!(game.solve().is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spread_goal_with_multi_moves_is_impossible() {
+        let mut game = game(3, 5);
+        game.spread_goal = true;
+        game.multi_move = true;
+        assert_eq!(game.required_moves(), RequiredMoves::Impossible);
+        assert!(game.solve().is_none());
+
+        game.disks_count = 1;
+        game.reset();
+        assert_eq!(game.required_moves(), RequiredMoves::Count(1), "a single disk is spread by moving it once");
+        assert!(game.shift(0, 1));
+        assert!(game.finished());
+    }
+
+    #[test]
+    fn optimal_remaining_counts_down_along_the_solution() {
+        for disks_count in 1..=10 {
+            let mut game = game(disks_count, 3);
+            let required_moves = game.required_moves().to_number();
+            assert_eq!(game.optimal_remaining(), Some(required_moves));
+            for (i, (from, to)) in game.solve().unwrap().into_iter().enumerate() {
+                game.shift(from, to);
+                assert_eq!(game.optimal_remaining(), Some(required_moves - i as u128 - 1));
+            }
         }
     }
+
+    #[test]
+    fn multi_moves_move_the_largest_group_that_fits() {
+        let mut game = HanoiGame {
+            multi_move: true,
+            merge_start: true,
+            ..game(5, 3)
+        };
+        game.reset();
+        // [5, 3, 1] and [4, 2]: only disk 1 fits on disk 2
+        assert_eq!(game.group_size(0, 1), 1);
+        assert!(game.shift(0, 1));
+        assert_eq!(game.group_size(0, 2), 2);
+        assert!(game.shift(0, 2));
+        assert_eq!(game.poles[2].as_slice(), [5, 3]);
+        assert!(game.shift(1, 2));
+        assert_eq!(game.poles[2].as_slice(), [5, 3, 2, 1]);
+        assert_eq!(game.poles[1].as_slice(), [4]);
+
+        assert!(!game.shift_group(2, 1, 5), "more disks than the pole holds");
+        assert!(!game.shift_group(1, 2, 1), "a larger disk on a smaller one");
+        game.multi_move = false;
+        assert!(!game.shift_group(2, 1, 2), "groups without multi-moves");
+    }
+
+    #[test]
+    fn multi_move_groups_stay_ordered_and_count_per_disk() {
+        let mut game = HanoiGame {
+            multi_move: true,
+            multi_move_counting: MultiMoveCounting::PerDisk,
+            illegal_moves: true,
+            ..game(4, 3)
+        };
+        game.poles[0].clear();
+        game.poles[0].extend([1, 4, 3, 2]);
+        assert_eq!(game.group_size(0, 1), 3, "disk 1 is under a larger disk");
+        assert_eq!(game.move_cost(0, 1), 3);
+        assert!(!game.shift_group(0, 1, 4));
+        assert!(game.shift(0, 1));
+        assert_eq!(game.poles[1].as_slice(), [4, 3, 2]);
+
+        let tower = HanoiGame {
+            multi_move: true,
+            ..self::game(6, 4)
+        };
+        assert_eq!(tower.required_moves(), RequiredMoves::Count(1));
+        assert_eq!(tower.solve(), Some(vec![(0, 1)]));
+    }
+
+    #[test]
+    fn placed_disks_count_from_the_bottom_of_the_target() {
+        let mut game = game(4, 3);
+        assert_eq!(game.placed_disks(), 0);
+        for (from, to) in game.solve().unwrap().into_iter().take(8) {
+            game.shift(from, to);
+        }
+        // disk 4 moved to the middle pole and disks 1 to 3 are on the last one
+        assert_eq!(game.placed_disks(), 1);
+        game.end_pole = Some(3);
+        assert_eq!(game.placed_disks(), 0);
+        game.end_pole = None;
+        for (from, to) in game.solve().unwrap() {
+            game.shift(from, to);
+        }
+        assert_eq!(game.placed_disks(), 4);
+    }
+
+    /// The recursive Frame-Stewart solver `solve_tower` unrolls.
+    fn recursive_tower(disks: usize, from: usize, to: usize, poles: &[usize], moves: &mut Vec<(usize, usize)>) {
+        match disks {
+            0 => {},
+            1 => moves.push((from, to)),
+            _ => {
+                let split = frame_stewart_split(disks, poles.len()).unwrap();
+                let aux = *poles.iter().find(|&&pole| pole != from && pole != to).unwrap();
+                let rest = poles.iter().copied().filter(|&pole| pole != aux).collect::<Vec<usize>>();
+                recursive_tower(split, from, aux, poles, moves);
+                recursive_tower(disks - split, from, to, &rest, moves);
+                recursive_tower(split, aux, to, poles, moves);
+            },
+        }
+    }
+
+    #[test]
+    fn iterative_solver_matches_the_recursion() {
+        for poles_count in 3..=6 {
+            let poles = (0..poles_count).collect::<Vec<usize>>();
+            for disks in 0..=12 {
+                let (mut iterative, mut recursive) = (vec![], vec![]);
+                solve_tower(disks, 0, poles_count - 1, &poles, &mut iterative).unwrap();
+                recursive_tower(disks, 0, poles_count - 1, &poles, &mut recursive);
+                assert_eq!(iterative, recursive, "{poles_count} poles, {disks} disks");
+            }
+        }
+    }
+
+    #[test]
+    fn solver_finishes_from_any_position() {
+        for disks_count in 1..=6 {
+            for end_pole in [None, Some(2), Some(3)] {
+                // every disk on the pole given by the digits of `position` in base 3, stacked legally
+                for position in 0..3usize.pow(disks_count as u32) {
+                    let mut game = HanoiGame { end_pole, ..game(disks_count, 3) };
+                    game.poles.iter_mut().for_each(|pole| pole.clear());
+                    for disk in (1..=disks_count).rev() {
+                        game.poles[position / 3usize.pow(disk as u32 - 1) % 3].push(disk);
+                    }
+                    let remaining = game.optimal_remaining().unwrap();
+                    let board = game.board();
+                    let moves = game.solve().unwrap();
+                    assert_eq!(game.board(), board, "solving doesn't move anything");
+                    assert_eq!(moves.len() as u128, remaining);
+                    for (from, to) in moves {
+                        assert!(game.shift(from, to));
+                    }
+                    assert!(game.finished(), "{disks_count} disks, position {position}, {end_pole:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solver_is_none_mid_game_without_a_formula() {
+        let mut game = game(5, 4);
+        game.shift(0, 1);
+        assert_eq!(game.solve(), None);
+        assert_eq!(game.restarted().solve().map(|moves| moves.len() as u128), Some(game.required_moves().to_number()));
+    }
+
+    #[test]
+    fn next_optimal_move_follows_the_solver() {
+        for (disks_count, poles_count) in [(7, 3), (9, 4), (10, 5)] {
+            let mut game = game(disks_count, poles_count);
+            for (from, to) in game.solve().unwrap() {
+                assert_eq!(game.next_optimal_move(), Some((from, to)));
+                game.shift(from, to);
+            }
+            assert_eq!(game.next_optimal_move(), None);
+        }
+
+        let mut game = game(6, 3);
+        game.shift(0, 1);
+        game.shift(0, 2);
+        assert_eq!(game.next_optimal_move(), game.solve().unwrap().first().copied());
+        // a huge tower doesn't need its whole solution, and being even it starts on the spare pole
+        assert_eq!(self::game(64, 3).next_optimal_move(), Some((0, 2)));
+    }
 }
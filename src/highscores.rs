@@ -1,13 +1,16 @@
-use std::time::Duration;
+use std::{fmt::Display, path::Path, time::{Duration, Instant}};
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use eframe::egui::ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
-use crate::HanoiApp;
+use crate::{hanoi::{HanoiGame, IllegalMoveCounting, MultiMoveCounting, MAX_DISKS, MAX_POLES}, play::InputSource, HanoiApp};
 
 pub type Highscores = AHashMap<Header, Vec<Score>>;
-pub type Move = (Duration, usize, usize);
+/// A move from one pole to another, with the time it was made at and the input that made it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct Move(pub Duration, pub usize, pub usize, #[serde(default)] pub Option<InputSource>);
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct Header {
@@ -15,8 +18,98 @@ pub struct Header {
     pub disks: usize,
     pub blindfold: bool,
     pub illegal_moves: bool,
+    #[serde(default)]
+    pub illegal_counting: IllegalMoveCounting,
     pub start_pole: usize,
     pub end_pole: Option<usize>,
+    #[serde(default)]
+    pub spread_goal: bool,
+    #[serde(default)]
+    pub merge_start: bool,
+    #[serde(default)]
+    pub multi_move: bool,
+    #[serde(default)]
+    pub multi_move_counting: MultiMoveCounting,
+    #[serde(default)]
+    pub outline: bool,
+}
+
+impl Header {
+    /// A game with these settings, at its starting position.
+    pub fn game(&self) -> HanoiGame {
+        let mut game = HanoiGame {
+            poles_count: self.poles,
+            disks_count: self.disks,
+            start_pole: self.start_pole,
+            end_pole: self.end_pole,
+            illegal_moves: self.illegal_moves,
+            illegal_counting: self.illegal_counting,
+            spread_goal: self.spread_goal,
+            merge_start: self.merge_start,
+            multi_move: self.multi_move,
+            multi_move_counting: self.multi_move_counting,
+            ..HanoiGame::new()
+        };
+        game.reset();
+        game
+    }
+    /// A code to share these settings with, without any moves.
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("headers are always serializable"))
+    }
+
+    pub fn decode(code: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD.decode(code.trim()).map_err(|err| format!("Invalid config code: {err}"))?;
+        let header: Self = serde_json::from_slice(&bytes).map_err(|err| format!("Invalid config code: {err}"))?;
+        header.validate().map_err(|err| format!("Invalid config code: {err}"))?;
+        Ok(header)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=MAX_POLES).contains(&self.poles) || !(1..=MAX_DISKS).contains(&self.disks) {
+            return Err(format!("{} poles and {} disks", self.poles, self.disks));
+        }
+        if !(1..=self.poles).contains(&self.start_pole) || self.end_pole.is_some_and(|end_pole| !(1..=self.poles).contains(&end_pole)) {
+            return Err("start or end pole out of range".to_string());
+        }
+        Ok(())
+    }
+
+    /// Orders headers by size first, then by rules.
+    pub fn sort_key(&self) -> impl Ord {
+        (self.poles, self.disks, self.start_pole, self.end_pole, self.blindfold, self.illegal_moves, self.illegal_counting, self.spread_goal, self.merge_start, self.multi_move, self.multi_move_counting, self.outline)
+    }
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}p {}d", self.poles, self.disks)?;
+        if self.start_pole != 1 {
+            write!(f, " from {}", self.start_pole)?;
+        }
+        if let Some(end_pole) = self.end_pole {
+            write!(f, " to {end_pole}")?;
+        }
+        if self.blindfold {
+            f.write_str(" blindfold")?;
+        }
+        if self.illegal_moves {
+            write!(f, " illegal ({:?})", self.illegal_counting)?;
+        }
+        if self.spread_goal {
+            f.write_str(" spread")?;
+        }
+        if self.merge_start {
+            f.write_str(" merge")?;
+        }
+        if self.multi_move {
+            write!(f, " multi ({:?})", self.multi_move_counting)?;
+        }
+        if self.outline {
+            f.write_str(" outline")?;
+        }
+        Ok(())
+    }
 }
 
 impl Default for Header {
@@ -26,8 +119,14 @@ impl Default for Header {
             poles: 3,
             disks: 5,
             illegal_moves: false,
+            illegal_counting: Default::default(),
             start_pole: 1,
             end_pole: None,
+            spread_goal: false,
+            merge_start: false,
+            multi_move: false,
+            multi_move_counting: Default::default(),
+            outline: false,
         }
     }
 }
@@ -38,37 +137,410 @@ pub struct Score {
     #[serde(default)]
     pub date: DateTime<Utc>,
     pub moves: Vec<Move>,
+    /// The oldest moves were dropped because of the move history limit,
+    /// so `moves` can't be replayed from the starting position.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// A replay along with the settings it was played with, so that it can be shared on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedReplay {
+    pub header: Header,
+    pub score: Score,
+}
+
+impl SharedReplay {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("replays are always serializable"))
+    }
+
+    pub fn decode(code: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD.decode(code.trim()).map_err(|err| format!("Invalid replay code: {err}"))?;
+        let replay: Self = serde_json::from_slice(&bytes).map_err(|err| format!("Invalid replay code: {err}"))?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    /// Saves the replay as a file, to share a single run along with its settings.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        std::fs::write(path, file).map_err(|err| format!("Unable to write {}: {err}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = std::fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+        let replay: Self = serde_json::from_str(&file).map_err(|err| format!("Invalid replay file: {err}"))?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let header = &self.header;
+        if self.score.truncated {
+            return Err("Invalid replay: the move history was truncated".to_string());
+        }
+        header.validate().map_err(|err| format!("Invalid replay: {err}"))?;
+        self.score.validate_moves(header).map_err(|err| format!("Invalid replay: {err}"))
+    }
+}
+
+/// Every run of one header, to share a category's whole history as a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderRuns {
+    pub header: Header,
+    pub scores: Vec<Score>,
+}
+
+impl HeaderRuns {
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+        std::fs::write(path, file).map_err(|err| format!("Unable to write {}: {err}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = std::fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+        let runs: Self = serde_json::from_str(&file).map_err(|err| format!("Invalid runs file: {err}"))?;
+        runs.validate().map_err(|err| format!("Invalid runs file: {err}"))?;
+        Ok(runs)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.header.validate()?;
+        self.scores.iter().try_for_each(|score| score.validate_moves(&self.header))
+    }
+
+    /// Saves every header's runs to one file. The headers can't be JSON keys, so they're saved as a list.
+    pub fn save_all(highscores: &Highscores, path: &Path) -> Result<(), String> {
+        let runs = highscores
+            .iter()
+            .map(|(&header, scores)| Self { header, scores: scores.clone() })
+            .collect::<Vec<Self>>();
+        let file = serde_json::to_string_pretty(&runs).map_err(|err| err.to_string())?;
+        std::fs::write(path, file).map_err(|err| format!("Unable to write {}: {err}", path.display()))
+    }
+
+    pub fn load_all(path: &Path) -> Result<Vec<Self>, String> {
+        let file = std::fs::read_to_string(path).map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+        let runs: Vec<Self> = serde_json::from_str(&file).map_err(|err| format!("Invalid high scores file: {err}"))?;
+        for header_runs in &runs {
+            header_runs.validate().map_err(|err| format!("Invalid high scores file: {err}"))?;
+        }
+        Ok(runs)
+    }
+}
+
+impl Score {
+    pub fn is_beaten_by(&self, time: Duration) -> bool {
+        time <= self.time
+    }
+    /// Whether both are the same run, even if one of them was truncated differently.
+    pub fn same_run(&self, other: &Self) -> bool {
+        self.time == other.time && self.date == other.date && self.moves == other.moves
+    }
+    pub fn validate_moves(&self, header: &Header) -> Result<(), String> {
+        match self.moves.iter().find(|&&Move(_, from, to, _)| from >= header.poles || to >= header.poles) {
+            Some(Move(_, from, to, _)) => Err(format!("move from pole {} to pole {}", from + 1, to + 1)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Totals over every finished game, kept apart from `Highscores`
+/// so that either can be cleared without touching the other.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub games: u64,
+    pub moves: u128,
+    pub time: Duration,
+}
+
+impl LifetimeStats {
+    pub fn record(&mut self, moves: u128, time: Duration) {
+        self.games += 1;
+        self.moves = self.moves.saturating_add(moves);
+        self.time = self.time.saturating_add(time);
+    }
+}
+
+/// How the runs of one header went.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HeaderStats {
+    pub runs: usize,
+    pub best: Duration,
+    pub mean: Duration,
+    pub median: Duration,
+    pub std_dev: Duration,
+    pub mean_moves: f64,
 }
 
 impl HanoiApp {
+    /// The statistics of the runs recorded with `header`, `None` without any.
+    pub fn header_stats(&self, header: &Header) -> Option<HeaderStats> {
+        let scores = self.highscores.get(header).filter(|scores| !scores.is_empty())?;
+        let mut times = scores.iter().map(|score| score.time).collect::<Vec<Duration>>();
+        times.sort();
+
+        let runs = times.len();
+        let mean = times.iter().sum::<Duration>() / runs as u32;
+        let median = match runs % 2 {
+            0 => (times[runs / 2 - 1] + times[runs / 2]) / 2,
+            _ => times[runs / 2],
+        };
+        let variance = times.iter().map(|time| (time.as_secs_f64() - mean.as_secs_f64()).powi(2)).sum::<f64>() / runs as f64;
+
+        Some(HeaderStats {
+            runs,
+            best: times[0],
+            mean,
+            median,
+            std_dev: Duration::from_secs_f64(variance.sqrt()),
+            mean_moves: scores.iter().map(|score| score.moves.len()).sum::<usize>() as f64 / runs as f64,
+        })
+    }
+
     pub fn get_current_header(&self) -> Header {
         Header {
             poles: self.hanoi.poles_count,
             disks: self.hanoi.disks_count,
             blindfold: self.blindfold,
             illegal_moves: self.hanoi.illegal_moves,
+            illegal_counting: if self.hanoi.illegal_moves { self.hanoi.illegal_counting } else { Default::default() },
             start_pole: self.hanoi.start_pole,
-            end_pole: self.hanoi.end_pole,
+            end_pole: if self.hanoi.spread_goal { None } else { self.hanoi.end_pole },
+            spread_goal: self.hanoi.spread_goal,
+            merge_start: self.hanoi.merge_start,
+            multi_move: self.hanoi.multi_move,
+            multi_move_counting: if self.hanoi.multi_move { self.hanoi.multi_move_counting } else { Default::default() },
+            outline: self.outline,
         }
     }
 
+    pub fn apply_header(&mut self, header: Header) {
+        self.hanoi.poles_count = header.poles;
+        self.hanoi.disks_count = header.disks;
+        self.blindfold = header.blindfold;
+        self.hanoi.illegal_moves = header.illegal_moves;
+        self.hanoi.illegal_counting = header.illegal_counting;
+        self.hanoi.start_pole = header.start_pole;
+        self.hanoi.end_pole = header.end_pole;
+        self.hanoi.spread_goal = header.spread_goal;
+        self.hanoi.merge_start = header.merge_start;
+        self.hanoi.multi_move = header.multi_move;
+        self.hanoi.multi_move_counting = header.multi_move_counting;
+        self.outline = header.outline;
+    }
+
     pub fn get_highscores_entry(&mut self, header: Header) -> &mut Vec<Score> {
         self.highscores.entry(header).or_default()
     }
 
     pub fn save_score(&mut self, duration: Duration) {
-        let header = self.get_current_header();
         let score = Score {
             time: duration,
             date: Utc::now() - duration,
             moves: self.hanoi.moves_history.clone(),
+            truncated: self.hanoi.history_truncated,
         };
+        if let Some(daily) = self.daily {
+            insert_by_time(self.daily_highscores.entry(daily).or_default(), score);
+            return
+        }
+        // puzzles don't start from the tower, so their times can't be compared
+        if self.hanoi.custom_start.is_some() { return }
+        let header = self.get_current_header();
+
+        self.lifetime_stats.record(self.moves, duration);
+
+        let new_highscore = self.get_highscores_entry(header).first().is_some_and(|highscore| highscore.is_beaten_by(duration));
+        self.insert_score(header, score);
+
+        if new_highscore {
+            self.celebration = Some(Instant::now());
+        }
+    }
+
+    /// The recorded header closest to `header`, where different rules weigh more than a different size.
+    pub fn nearest_header(&self, header: Header) -> Option<Header> {
+        let distance = |other: &Header| {
+            let flags = [
+                header.blindfold != other.blindfold,
+                header.illegal_moves != other.illegal_moves,
+                header.illegal_counting != other.illegal_counting,
+                header.spread_goal != other.spread_goal,
+                header.merge_start != other.merge_start,
+                header.multi_move != other.multi_move,
+                header.multi_move_counting != other.multi_move_counting,
+                header.outline != other.outline,
+            ];
+            let end_pole = match (header.end_pole, other.end_pole) {
+                (Some(a), Some(b)) => a.abs_diff(b),
+                (None, None) => 0,
+                _ => 1,
+            };
+            flags.iter().filter(|&&flag| flag).count() * MAX_DISKS * MAX_POLES
+                + header.disks.abs_diff(other.disks)
+                + header.poles.abs_diff(other.poles)
+                + header.start_pole.abs_diff(other.start_pole)
+                + end_pole
+        };
+
+        self.highscores
+            .iter()
+            .filter(|(_, scores)| !scores.is_empty())
+            .map(|(other, _)| *other)
+            // ties are broken the same way every time, whatever the map order
+            .min_by_key(|other| (distance(other), other.disks, other.poles, other.start_pole, other.end_pole))
+    }
 
-        let entry = self.get_highscores_entry(header);
-        if let Some((i, _)) = entry.iter().enumerate().find(|(_,s)| score.time < s.time) {
-            entry.insert(i, score);
-        } else {
-            entry.push(score);
+    /// Adds the runs that aren't recorded yet to their header, returning how many were new.
+    pub fn merge_runs(&mut self, runs: HeaderRuns) -> usize {
+        let mut added = 0;
+        for score in runs.scores {
+            if !self.get_highscores_entry(runs.header).iter().any(|recorded| recorded.same_run(&score)) {
+                self.insert_score(runs.header, score);
+                added += 1;
+            }
         }
+        added
+    }
+
+    /// Merges the runs of every header, returning how many were new.
+    pub fn merge_all_runs(&mut self, runs: Vec<HeaderRuns>) -> usize {
+        runs.into_iter().map(|header_runs| self.merge_runs(header_runs)).sum()
+    }
+
+    /// Adds a score to its header, keeping the scores sorted by time.
+    pub fn insert_score(&mut self, header: Header, score: Score) {
+        insert_by_time(self.get_highscores_entry(header), score);
+    }
+}
+
+/// Keeps `scores` sorted from the fastest, a tie goes after the runs already there.
+pub fn insert_by_time(scores: &mut Vec<Score>, score: Score) {
+    if let Some((i, _)) = scores.iter().enumerate().find(|(_,s)| score.time < s.time) {
+        scores.insert(i, score);
+    } else {
+        scores.push(score);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::HanoiApp;
+
+    use crate::play::InputSource;
+
+    use super::{Header, HeaderRuns, Move, Score, SharedReplay};
+
+    #[test]
+    fn config_codes_round_trip_and_validate() {
+        let header = Header { poles: 5, disks: 12, start_pole: 2, end_pole: Some(5), blindfold: true, ..Default::default() };
+        assert_eq!(Header::decode(&header.encode()), Ok(header));
+
+        for invalid in [
+            Header { poles: 1, ..Default::default() },
+            Header { disks: 0, ..Default::default() },
+            Header { start_pole: 4, ..Default::default() },
+            Header { end_pole: Some(0), ..Default::default() },
+        ] {
+            assert!(Header::decode(&invalid.encode()).is_err(), "{invalid:?}");
+        }
+        assert!(Header::decode("not a code").is_err());
+    }
+
+    #[test]
+    fn merging_runs_skips_the_recorded_ones() {
+        let header = Header { disks: 3, ..Default::default() };
+        let score = |millis| Score { time: Duration::from_millis(millis), ..Default::default() };
+
+        let mut app = HanoiApp::default();
+        app.insert_score(header, score(2000));
+        let added = app.merge_runs(HeaderRuns { header, scores: vec![score(3000), score(2000), score(1000)] });
+
+        assert_eq!(added, 2);
+        assert_eq!(app.highscores[&header], vec![score(1000), score(2000), score(3000)]);
+    }
+
+    #[test]
+    fn replay_files_round_trip() {
+        let header = Header { poles: 4, disks: 5, blindfold: true, ..Default::default() };
+        let score = Score {
+            time: Duration::from_millis(1234),
+            date: chrono::Utc::now(),
+            moves: vec![Move(Duration::from_millis(10), 0, 3, None), Move(Duration::from_millis(1234), 0, 1, Some(InputSource::SwiftKeys))],
+            truncated: false,
+        };
+        let replay = SharedReplay { header, score };
+        let path = std::env::temp_dir().join(format!("hanoi-speedrapp-replay-{}.hanoi", std::process::id()));
+
+        replay.save(&path).unwrap();
+        assert_eq!(SharedReplay::load(&path), Ok(replay.clone()));
+
+        let corrupt = SharedReplay { header: Header { poles: 3, ..header }, ..replay };
+        corrupt.save(&path).unwrap();
+        assert!(SharedReplay::load(&path).is_err(), "a move to pole 4 with 3 poles");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn highscores_files_merge_without_duplicates() {
+        let headers = [Header { disks: 3, ..Default::default() }, Header { disks: 5, poles: 4, ..Default::default() }];
+        let score = |millis| Score { time: Duration::from_millis(millis), ..Default::default() };
+        let path = std::env::temp_dir().join(format!("hanoi-speedrapp-highscores-{}.json", std::process::id()));
+
+        let mut app = HanoiApp::default();
+        app.insert_score(headers[0], score(2000));
+        app.insert_score(headers[0], score(1000));
+        app.insert_score(headers[1], score(5000));
+        HeaderRuns::save_all(&app.highscores, &path).unwrap();
+
+        let mut other = HanoiApp::default();
+        other.insert_score(headers[0], score(1500));
+        assert_eq!(other.merge_all_runs(HeaderRuns::load_all(&path).unwrap()), 3);
+        assert_eq!(other.merge_all_runs(HeaderRuns::load_all(&path).unwrap()), 0, "importing twice adds nothing");
+        assert_eq!(other.highscores[&headers[0]], vec![score(1000), score(1500), score(2000)]);
+        assert_eq!(other.highscores[&headers[1]], vec![score(5000)]);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(HeaderRuns::load_all(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn header_stats_of_the_recorded_runs() {
+        let header = Header { disks: 3, ..Default::default() };
+        let score = |millis, moves| Score {
+            time: Duration::from_millis(millis),
+            moves: vec![Move(Duration::ZERO, 0, 1, None); moves],
+            ..Default::default()
+        };
+
+        let mut app = HanoiApp::default();
+        assert_eq!(app.header_stats(&header), None);
+        app.highscores.insert(header, vec![]);
+        assert_eq!(app.header_stats(&header), None, "an empty header has no data");
+
+        app.insert_score(header, score(2000, 7));
+        let stats = app.header_stats(&header).unwrap();
+        assert_eq!(stats.runs, 1);
+        assert_eq!(stats.best, Duration::from_millis(2000));
+        assert_eq!(stats.median, Duration::from_millis(2000));
+        assert_eq!(stats.std_dev, Duration::ZERO);
+        assert_eq!(stats.mean_moves, 7.0);
+
+        app.insert_score(header, score(4000, 9));
+        app.insert_score(header, score(1000, 7));
+        app.insert_score(header, score(5000, 9));
+        let stats = app.header_stats(&header).unwrap();
+        assert_eq!(stats.runs, 4);
+        assert_eq!(stats.best, Duration::from_millis(1000));
+        assert_eq!(stats.mean, Duration::from_millis(3000));
+        assert_eq!(stats.median, Duration::from_millis(3000));
+        assert!(stats.std_dev.abs_diff(Duration::from_secs_f64(2.5f64.sqrt())) < Duration::from_micros(1));
+        assert_eq!(stats.mean_moves, 8.0);
     }
 }
@@ -1,35 +1,51 @@
 use std::time::{Duration, Instant};
 
 use arrayvec::ArrayVec;
-use chrono::Datelike;
+use backup::{auto_backup, restore_backup, BackupSettings};
 use clap::Parser;
 use cli::Cli;
-use display::{themes::ColorTheme, PolesPosition};
-use eframe::{egui::{self, Key}, App, Frame, HardwareAcceleration, NativeOptions, APP_KEY};
-use highscores::{Header, Highscores};
-use play::{PlayerKind, HUMAN_PLAY};
+use daily::{BeforeDaily, DailyHeader, DailyHighscores};
+use display::{diagnostics::Diagnostics, frames::FrameExport, themes::{AppVisuals, ColorTheme}, CompletedStats, DiskNumberSettings, DiskShape, HudSettings, PolesPosition, ShareTimeFormat, WindowGeometry, DEFAULT_HANOI_APP, MIN_WINDOW_SIZE};
+use eframe::{egui::{self, ahash::AHashMap, Key, ThemePreference, ViewportBuilder}, App, Frame, HardwareAcceleration, NativeOptions, APP_KEY};
+use highscores::{Header, Highscores, LifetimeStats, SharedReplay};
+use play::{load_quick_keys, GamepadAction, GamepadSettings, Ghost, PlayerKind, QuickKey, HUMAN_PLAY};
 use profiling::enable_profiling;
 use serde::{Deserialize, Serialize};
 use hanoi::{HanoiGame, MAX_POLES};
 use serde_with::{serde_as, DefaultOnError};
+use puzzles::PuzzlePreset;
+use trainer::Trainer;
 use util::*;
 
+mod backup;
 mod hanoi;
 mod cli;
 mod display;
 mod play;
+mod puzzles;
 mod highscores;
 mod util;
 mod profiling;
+mod sound;
+mod trainer;
+mod daily;
+#[cfg(feature = "spectator")]
+mod spectator;
 
 const APP_NAME: &str = "Towers of Hanoi - Speedrapp Edition";
+/// How often a watched game is checked for changes while it isn't running.
+const SPECTATOR_REFRESH: Duration = Duration::from_millis(50);
 
 type PolesVec<T> = ArrayVec<T, {MAX_POLES}>;
 
 fn main() -> Result<(), eframe::Error> {
     let cli = Cli::parse();
     if cli.profile { enable_profiling() }
-    if cli.backup { backup_save() }
+    if let Some(path) = &cli.restore {
+        if let Err(err) = restore_backup(path) {
+            eprintln!("{err}");
+        }
+    }
     hide_console_window();
 
     HanoiApp::run(cli)
@@ -52,7 +68,7 @@ enum GameState {
 // }
 
 #[serde_as]
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct HanoiApp {
     #[serde(default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
@@ -72,11 +88,23 @@ struct HanoiApp {
     #[serde(default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
     reset_on_invalid_move: bool,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    reset_mode: ResetMode,
+    /// How the current game was last restarted, when it kept something from the attempt before.
+    #[serde(skip)]
+    retried: Option<ResetMode>,
+    #[serde(default = "move_history_limit")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    move_history_limit: Option<usize>,
 
     // display
     #[serde(default = "falsy")]
     #[serde_as(deserialize_as = "DefaultOnError")]
     blindfold: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    outline: bool,
     #[serde(default = "truthy")]
     #[serde_as(deserialize_as = "DefaultOnError")]
     show_poles: bool,
@@ -85,24 +113,207 @@ struct HanoiApp {
     disk_number: bool,
     #[serde(default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
+    disk_number_settings: DiskNumberSettings,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    afterimage: bool,
+    #[serde(skip)]
+    last_move: Option<(usize, usize, Instant)>,
+    /// Interpolates replayed moves, off for frame accurate study.
+    #[serde(default = "truthy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    smooth_replays: bool,
+    /// From, to, start and duration of the replayed move being animated.
+    #[serde(skip)]
+    replay_glide: Option<(usize, usize, Instant, Duration)>,
+    /// When the replay was paused, the clock of the replay stops until it resumes.
+    #[serde(skip)]
+    replay_paused_since: Option<Instant>,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    target_preview: bool,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
     color_theme: ColorTheme,
+    /// A pasted list of hex colors, to turn into a custom theme.
+    #[serde(skip)]
+    palette_text: String,
     #[serde(default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
     poles_position: PolesPosition,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    disk_shape: DiskShape,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    mirror_poles: bool,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    share_time_format: ShareTimeFormat,
+    #[serde(default = "app_theme")]
+    app_theme: ThemePreference,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    app_visuals: AppVisuals,
+    #[serde(default = "truthy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    celebrate_highscore: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    auto_share: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    dim_completed: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    replay_inputs: bool,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    backup: BackupSettings,
+    /// Start every game with moves that don't count, until the begin key is pressed.
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    warmup: bool,
+    #[serde(skip)]
+    warming_up: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    shuffle_colors: bool,
+    /// The seed the disk colors of the current game are shuffled with.
+    #[serde(skip)]
+    color_seed: Option<u64>,
+    /// Play replays back at an even pace instead of the recorded one.
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    adaptive_replays: bool,
+    /// When each move of the current replay is played, when it differs from the recorded times.
+    #[serde(skip)]
+    replay_schedule: Vec<Duration>,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    puzzle_presets: Vec<PuzzlePreset>,
+    #[serde(skip)]
+    puzzle_name: String,
+    /// Start every game from a random position drawn from `scramble_seed`, instead of the tower.
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    scramble: bool,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    scramble_seed: u64,
+    /// Keep dealing the position of `scramble_seed`, instead of a new one every game.
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    fixed_seed: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    show_placed_disks: bool,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    show_hint: bool,
+    /// Plays the best run of the current settings faded behind the live game.
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    ghost_enabled: bool,
+    #[serde(skip)]
+    ghost: Option<Ghost>,
+    /// The next move of the optimal path, kept up to date after every move while hints are shown.
+    #[serde(skip)]
+    hint: Option<(usize, usize)>,
+    /// How long a moved disk takes to glide to its pole, zero makes it jump.
+    #[serde(default = "animation_duration")]
+    animation_duration: Duration,
+    /// The pole the last moved disk left and reached, along with when it left and how long it takes.
+    #[serde(skip)]
+    move_animation: Option<(usize, usize, Instant, Duration)>,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    sound_enabled: bool,
+    /// The volume of the sound effects and the metronome, from 0 to 1.
+    #[serde(default = "volume")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    volume: f32,
+    /// Ticks at this many beats per minute while playing, to pace the moves evenly.
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    metronome_bpm: Option<u32>,
+    /// When the metronome last ticked.
+    #[serde(skip)]
+    metronome_tick: Option<Instant>,
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    confirm_close: bool,
+    /// Plays tones for the selected pole, the moved disk and its poles, so the game can be followed by ear.
+    #[serde(default = "falsy")]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    sonification: bool,
+    #[serde(skip)]
+    close_prompt: bool,
+    #[serde(skip)]
+    close_confirmed: bool,
+    #[serde(skip)]
+    celebration: Option<Instant>,
+    #[serde(skip)]
+    toast: Option<(String, Instant)>,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    hud: HudSettings,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    diagnostics: Diagnostics,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    trainer: Trainer,
 
     // input
     #[serde(default = "reset_key")]
     reset_key: Key,
     #[serde(default = "undo_key")]
     undo_key: Key,
+    #[serde(default = "redo_key")]
+    redo_key: Key,
+    #[serde(default = "bot_key")]
+    bot_key: Key,
+    #[serde(default = "begin_key")]
+    begin_key: Key,
+    #[serde(default = "rematch_key")]
+    rematch_key: Key,
+    #[serde(default = "disk_number_key")]
+    disk_number_key: Key,
+    #[serde(default = "step_key")]
+    step_key: Key,
+    #[serde(default = "pause_key")]
+    pause_key: Key,
     #[serde(default = "quick_keys")]
-    quick_keys: Vec<(Key, usize, usize)>,
+    quick_keys: Vec<QuickKey>,
+    /// The key of every pole for swift keys, the poles past the end of the list can't be selected with them.
+    #[serde(default = "swift_keys")]
+    swift_keys: Vec<Key>,
+    #[serde(skip)]
+    quick_keys_error: Option<String>,
+    /// Which ways of playing by hand are on, by name so that saves outlive the ones that get added or removed.
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    human_plays: AHashMap<String, bool>,
     #[serde(skip, default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
     dragging_pole: Option<usize>,
     #[serde(skip, default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
     swift_pole: Option<usize>,
+    /// The pole under the cursor of the cycle keys.
+    #[serde(skip)]
+    cycle_pole: Option<usize>,
+    #[serde(default = "cycle_key")]
+    cycle_key: Key,
+    #[serde(default = "confirm_key")]
+    confirm_key: Key,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    gamepad: GamepadSettings,
+    /// The action waiting for a gamepad input to be bound to it.
+    #[serde(skip)]
+    gamepad_binding: Option<GamepadAction>,
 
     // windows
     #[serde(default = "falsy")]
@@ -114,6 +325,11 @@ struct HanoiApp {
     #[serde(default = "falsy")]
     #[serde_as(deserialize_as = "DefaultOnError")]
     input_display_window: bool,
+    #[serde(skip, default = "truthy")]
+    completed_window: bool,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    completed_stats: CompletedStats,
 
     #[serde(default = "truthy")]
     #[serde_as(deserialize_as = "DefaultOnError")]
@@ -128,7 +344,50 @@ struct HanoiApp {
     highscores: Highscores,
     #[serde(default)]
     #[serde_as(deserialize_as = "DefaultOnError")]
+    daily_highscores: DailyHighscores,
+    /// The daily challenge being played, its settings are locked until it's left.
+    #[serde(skip)]
+    daily: Option<DailyHeader>,
+    #[serde(skip)]
+    before_daily: Option<BeforeDaily>,
+    /// The player's own game while watching someone else's, which replaces it on screen.
+    #[serde(skip)]
+    before_spectating: Option<HanoiGame>,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    lifetime_stats: LifetimeStats,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
     replays_filter: Header,
+    /// Where the window was and how big, to open it the same way next time.
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    window: Option<WindowGeometry>,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    frame_export: FrameExport,
+    #[serde(skip)]
+    frame_export_result: Option<Result<usize, String>>,
+    #[serde(skip)]
+    runs_file_result: Option<Result<String, String>>,
+    #[serde(default = "graph_height")]
+    graph_height: u32,
+    #[serde(skip)]
+    replay_code: String,
+    #[serde(skip)]
+    config_code: String,
+    #[serde(skip)]
+    decoded_config: Option<Result<Header, String>>,
+    #[serde(skip)]
+    decoded_replay: Option<Result<SharedReplay, String>>,
+    #[serde(skip)]
+    attempts: AHashMap<Header, usize>,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    goal_times: AHashMap<Header, Duration>,
+    #[serde(default)]
+    #[serde_as(deserialize_as = "DefaultOnError")]
+    last_header: Option<Header>,
 }
 
 impl Default for HanoiApp {
@@ -140,40 +399,134 @@ impl Default for HanoiApp {
             moves: 0,
             undo_index: 0,
             reset_on_invalid_move: false,
+            reset_mode: Default::default(),
+            retried: None,
+            move_history_limit: move_history_limit(),
 
             blindfold: false,
+            outline: false,
             show_poles: true,
             disk_number: false,
+            disk_number_settings: Default::default(),
+            afterimage: false,
+            last_move: None,
+            smooth_replays: true,
+            replay_glide: None,
+            replay_paused_since: None,
+            target_preview: false,
             color_theme: Default::default(),
+            palette_text: String::new(),
             poles_position: Default::default(),
+            disk_shape: Default::default(),
+            mirror_poles: false,
+            share_time_format: Default::default(),
+            app_theme: app_theme(),
+            app_visuals: Default::default(),
+            celebrate_highscore: true,
+            auto_share: false,
+            dim_completed: false,
+            replay_inputs: false,
+            backup: Default::default(),
+            warmup: false,
+            warming_up: false,
+            shuffle_colors: false,
+            color_seed: None,
+            adaptive_replays: false,
+            replay_schedule: vec![],
+            puzzle_presets: vec![],
+            puzzle_name: String::new(),
+            scramble: false,
+            scramble_seed: 0,
+            fixed_seed: false,
+            show_placed_disks: false,
+            show_hint: false,
+            ghost_enabled: false,
+            ghost: None,
+            hint: None,
+            animation_duration: animation_duration(),
+            move_animation: None,
+            sound_enabled: false,
+            volume: volume(),
+            metronome_bpm: None,
+            metronome_tick: None,
+            confirm_close: false,
+            sonification: false,
+            close_prompt: false,
+            close_confirmed: false,
+            celebration: None,
+            toast: None,
+            hud: Default::default(),
+            diagnostics: Default::default(),
+            trainer: Default::default(),
 
             reset_key: reset_key(),
             undo_key: undo_key(),
+            redo_key: redo_key(),
+            rematch_key: rematch_key(),
+            bot_key: bot_key(),
+            begin_key: begin_key(),
+            disk_number_key: disk_number_key(),
+            step_key: step_key(),
+            pause_key: pause_key(),
             quick_keys: quick_keys(),
+            swift_keys: swift_keys(),
+            quick_keys_error: None,
+            human_plays: Default::default(),
             dragging_pole: None,
             swift_pole: None,
+            cycle_pole: None,
+            cycle_key: cycle_key(),
+            confirm_key: confirm_key(),
+            gamepad: Default::default(),
+            gamepad_binding: None,
 
             settings_window: false,
             replays_window: false,
             input_display_window: false,
+            completed_window: true,
+            completed_stats: Default::default(),
 
             infos_panel: true,
 
             extra_mode: false,
 
             highscores: Default::default(),
+            daily_highscores: Default::default(),
+            daily: None,
+            before_daily: None,
+            before_spectating: None,
+            lifetime_stats: Default::default(),
             replays_filter: Default::default(),
+            window: None,
+            frame_export: Default::default(),
+            frame_export_result: None,
+            runs_file_result: None,
+            graph_height: graph_height(),
+            replay_code: String::new(),
+            config_code: String::new(),
+            decoded_config: None,
+            decoded_replay: None,
+            attempts: Default::default(),
+            goal_times: Default::default(),
+            last_header: None,
         }
     }
 }
 
 impl HanoiApp {
     pub fn run(cli: Cli) -> Result<(), eframe::Error> {
+        #[cfg(feature = "spectator")]
+        spectator::start_spectator(&cli);
+
         let options = NativeOptions {
             hardware_acceleration: HardwareAcceleration::Preferred,
             vsync: cli.vsync,
 
-            persist_window: true,
+            // the window geometry is saved with the app, and restored once it's loaded
+            persist_window: false,
+            viewport: ViewportBuilder::default()
+                .with_inner_size(DEFAULT_HANOI_APP.default_window_size())
+                .with_min_inner_size(MIN_WINDOW_SIZE),
 
             ..Default::default()
         };
@@ -182,12 +535,21 @@ impl HanoiApp {
             APP_NAME,
             options,
             Box::new(|cc| Ok(
-                Box::new(if let Some(storage) = cc.storage {
-                    let mut app = eframe::get_value::<HanoiApp>(storage, eframe::APP_KEY).unwrap_or_default();
+                Box::new({
+                    let saved_app = cc.storage.and_then(|storage| eframe::get_value::<HanoiApp>(storage, APP_KEY));
+                    auto_backup(saved_app.as_ref().map(|app| app.backup).unwrap_or_default(), cli.backup);
+                    let mut app = saved_app.unwrap_or_default();
+                    app.restore_window(&cc.egui_ctx);
+                    if let Some(path) = cli.quick_keys {
+                        match load_quick_keys(&path) {
+                            Ok(quick_keys) => app.quick_keys = quick_keys,
+                            Err(err) => eprintln!("Unable to load the quick keys: {err}"),
+                        }
+                    }
                     app.soft_reset();
+                    cc.egui_ctx.set_theme(app.app_theme);
+                    app.app_visuals.apply(&cc.egui_ctx);
                     app
-                } else {
-                    HanoiApp::default()
                 })
             )),
         )
@@ -196,63 +558,91 @@ impl HanoiApp {
 
 impl App for HanoiApp {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        eframe::set_value(storage, eframe::APP_KEY, &*self.saved());
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
         puffin::profile_function!();
 
+        self.diagnostics.start_frame();
+        self.track_window(ctx);
         self.check_extra_mode(ctx);
 
-        match self.player {
-            PlayerKind::Human => {
-                (*HUMAN_PLAY).lock().iter_mut().for_each(|p| p.context_play(self, ctx));
-                match self.state {
-                    GameState::Playing(start) if self.hanoi.finished() => {
-                        let elapsed = start.elapsed();
-                        self.state = GameState::Finished(elapsed);
-                        self.save_score(elapsed);
-                    },
-                    _ => {},
+        #[cfg(feature = "spectator")]
+        self.spectator_update();
+
+        if !self.spectating() {
+            match self.player {
+                PlayerKind::Human => {
+                    for play in (*HUMAN_PLAY).lock().iter_mut() {
+                        if self.is_human_play_enabled(play.source()) {
+                            play.context_play(self, ctx);
+                        }
+                    }
+                    self.ghost_play();
+                    self.check_finished(ctx);
+                },
+                PlayerKind::Bot => self.bot_play(),
+                PlayerKind::StepBot(..) => self.check_finished(ctx),
+                PlayerKind::Replay(..) => self.replay_play(),
+            }
+        }
+
+        // typing in a text field doesn't trigger the hotkeys
+        if !ctx.wants_keyboard_input() {
+            ctx.input(|i| {
+                if i.key_pressed(self.reset_key) {
+                    self.retry();
                 }
-            },
-            PlayerKind::Bot => self.bot_play(),
-            PlayerKind::Replay(..) => self.replay_play(),
-        };
+                if i.key_pressed(self.rematch_key) {
+                    self.rematch();
+                }
+                if i.key_pressed(self.bot_key) {
+                    self.toggle_bot();
+                }
+                if i.key_pressed(self.begin_key) {
+                    self.end_warmup();
+                }
+                // undoing works whichever ways to play are on
+                if matches!((&self.player, &self.state), (PlayerKind::Human, GameState::Playing(_))) {
+                    if i.key_pressed(self.undo_key) {
+                        self.undo_move();
+                    }
+                    if i.key_pressed(self.redo_key) {
+                        self.redo_move();
+                    }
+                }
+                if i.key_pressed(self.step_key) {
+                    self.step_bot_advance();
+                }
+                if i.key_pressed(self.pause_key) {
+                    self.toggle_replay_pause();
+                }
+                if i.key_pressed(self.disk_number_key) {
+                    self.disk_number ^= true;
+                    self.show_toast(format!("Disk numbers {}", if self.disk_number { "shown" } else { "hidden" }));
+                }
+            });
+        }
 
-        ctx.input(|i| {
-            if i.key_pressed(self.reset_key) {
-                self.soft_reset();
-            }
-        });
+        self.metronome_update(ctx);
 
         self.draw_top_bar(ctx);
         self.draw_infos_panel(ctx);
         self.draw_central_panel(ctx);
 
-        if matches!(self.state, GameState::Playing(_)) {
+        if matches!(self.state, GameState::Playing(_)) || (0..self.hanoi.poles_count).any(|i| self.afterimage_on(i).is_some() || self.replay_glide_on(i).is_some() || self.move_animation_on(i).is_some()) {
             ctx.request_repaint();
+        } else if self.spectating() {
+            ctx.request_repaint_after(SPECTATOR_REFRESH);
         }
     }
 }
 
-fn backup_save() {
-    if let Some(path) = eframe::storage_dir(APP_NAME) {
-        let main_file = path.join(format!("{APP_KEY}.ron"));
-
-        // let's assume that this process does not take a full day to complete
-        let now = chrono::Utc::now();
-        let year = now.year();
-        let month = now.month();
-        let day = now.day();
-
-        for i in 0..1000 {
-            let postfix = if i == 0 { "".to_string() } else { format!(" {}", i) };
-            let output_file = path.join(format!("{APP_KEY} BACKUP {year}_{month}_{day}{postfix}.ron"));
-            if !output_file.exists() && std::fs::copy(&main_file, &output_file).is_ok() {
-                break;
-            }
-        }
+#[cfg(not(feature = "spectator"))]
+impl HanoiApp {
+    pub fn spectating(&self) -> bool {
+        false
     }
 }
 
@@ -5,16 +5,23 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 
-use crate::{highscores::Score, GameState, HanoiApp, PolesVec};
+use crate::{highscores::{Move, Score}, util::ResetMode, GameState, HanoiApp, PolesVec};
 
 mod bot;
 mod replay;
 
+pub use gamepad::{connected_gamepads, GamepadAction, GamepadInput, GamepadSettings};
+pub use quick_keys::{load_quick_keys, out_of_range_quick_keys, save_quick_keys, QuickInput, QuickKey};
+pub use replay::Ghost;
+pub use swift_keys::SWIFT_KEYS;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
 pub enum PlayerKind {
     #[default]
     Human,
     Bot,
+    /// The bot's moves, played one at a time by the player, along with how many were played.
+    StepBot(Vec<(usize, usize)>, usize),
     Replay(Score, usize),
 }
 
@@ -25,13 +32,37 @@ pub trait Play {
 }
 
 macro_rules! human_play {
-    ($($mod:ident => $struct:ident,)*) => {
+    ($($mod:ident => $struct:ident ($label:literal),)*) => {
         $(mod $mod;)*
 
         pub enum HumanPlay {
             $($struct($mod::$struct),)*
         }
+        /// Which play mode made a move.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum InputSource {
+            $($struct,)*
+        }
+        impl InputSource {
+            pub const ALL: [InputSource; HUMAN_PLAY_COUNT] = [$(InputSource::$struct,)*];
+            /// The name its enable flag is saved under.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(InputSource::$struct => stringify!($struct),)*
+                }
+            }
+            pub fn label(self) -> &'static str {
+                match self {
+                    $(InputSource::$struct => $label,)*
+                }
+            }
+        }
         impl HumanPlay {
+            pub fn source(&self) -> InputSource {
+                match self {
+                    $(HumanPlay::$struct(_) => InputSource::$struct,)*
+                }
+            }
             pub fn context_play(&mut self, app: &mut HanoiApp, ctx: &egui::Context) {
                 match self {
                     $(HumanPlay::$struct(play) => play.context_play(app, ctx),)*
@@ -48,7 +79,9 @@ macro_rules! human_play {
                 }
             }
         }
-        pub static HUMAN_PLAY: Lazy<Arc<Mutex<[HumanPlay; [$(stringify!($mod),)*].len()]>>> = Lazy::new(|| Arc::new(Mutex::new(
+        /// How many ways there are to play by hand.
+        pub const HUMAN_PLAY_COUNT: usize = [$(stringify!($mod),)*].len();
+        pub static HUMAN_PLAY: Lazy<Arc<Mutex<[HumanPlay; HUMAN_PLAY_COUNT]>>> = Lazy::new(|| Arc::new(Mutex::new(
             [
                 $(
                     HumanPlay::$struct($mod::$struct::default()),
@@ -59,35 +92,434 @@ macro_rules! human_play {
 }
 
 human_play!{
-    quick_keys => QuickKeys,
-    swift_keys => SwiftKeys,
-    drag_and_drop => DragAndDrop,
+    quick_keys => QuickKeys("Quick keys"),
+    swift_keys => SwiftKeys("Swift keys"),
+    drag_and_drop => DragAndDrop("Drag and drop"),
+    cycle_keys => CycleKeys("Cycle keys"),
+    gamepad => GamepadPlay("Gamepad"),
 }
 
 impl HanoiApp {
-    pub fn full_move(&mut self, from: usize, to: usize) {
+    pub fn full_move(&mut self, from: usize, to: usize, input: Option<InputSource>) {
+        let count = self.hanoi.group_size(from, to);
+        self.full_group_move(from, to, count, input);
+    }
+    /// Moves the top `count` disks of `from` onto `to`, more than one only with multi-moves enabled.
+    fn full_group_move(&mut self, from: usize, to: usize, count: usize, input: Option<InputSource>) {
+        // the watched board gets replaced every frame
+        if self.spectating() { return }
+        if !self.hanoi.in_play(from) || !self.hanoi.in_play(to) { return }
+        if self.warming_up {
+            // the clock, the counter and the history wait for the warmup to end
+            self.shift_group(from, to, count, input);
+            return;
+        }
         if !matches!(self.state, GameState::Finished(_)) {
-            if self.hanoi.shift(from, to) {
+            let cost = self.hanoi.group_move_cost(from, to, count);
+            if self.shift_group(from, to, count, input) {
                 if self.state == GameState::Reset {
                     self.state = GameState::Playing(Instant::now());
+                    let header = self.get_current_header();
+                    if self.retried != Some(ResetMode::BoardOnly) {
+                        *self.attempts.entry(header).or_default() += 1;
+                    }
+                    self.last_header = Some(header);
                 }
-                self.moves += 1;
+                self.moves += cost;
                 if let GameState::Playing(time) = self.state {
-                    self.hanoi.moves_history.push((time.elapsed(), from, to));
+                    // a new move replaces the undone ones, so they can't be redone anymore
+                    self.hanoi.moves_history.truncate(self.undo_index);
+                    self.hanoi.moves_history.push(Move(time.elapsed(), from, to, input));
+                    self.undo_index = self.hanoi.moves_history.len();
+                    self.limit_history();
                 }
             } else if self.reset_on_invalid_move {
                 self.soft_reset();
             }
         }
     }
+    /// Moves the disks on the board with the sounds, the animation and the hint that go with it,
+    /// without touching the counter or the history.
+    fn shift_group(&mut self, from: usize, to: usize, count: usize, input: Option<InputSource>) -> bool {
+        let moved = self.hanoi.shift_group(from, to, count);
+        self.sonify_move(from, to, moved);
+        self.sound_drop(moved);
+        if moved {
+            self.record_last_move(from, to);
+            self.start_move_animation(from, to, input);
+            self.update_hint();
+        }
+        moved
+    }
+    /// Picks `pole` to take a disk from, or moves the picked disk onto it, the way swift keys play.
+    pub fn swift_select(&mut self, pole: usize, input: InputSource) {
+        self.swift_pole = match self.swift_pole {
+            None => {
+                self.sonify_selection(pole);
+                self.sound_pick();
+                Some(pole)
+            },
+            Some(from) => {
+                self.full_move(from, pole, Some(input));
+                None
+            }
+        }
+    }
+    /// Takes back the last move: the board, the counter and the history go back to how they were before it.
     pub fn undo_move(&mut self) {
-        if let Some((_, from, to)) = self.undo_index.checked_sub(1).and_then(|i| self.hanoi.moves_history.get(i)) {
-            self.full_move(*to, *from);
-            self.undo_index -= 1;
+        if self.spectating() || !matches!(self.state, GameState::Playing(_)) { return }
+        let Some(index) = self.undo_index.checked_sub(1) else { return };
+        let Some(&Move(_, from, to, _)) = self.hanoi.moves_history.get(index) else { return };
+        // a group has to come back whole, even if more disks would fit back on `from`
+        let count = self.hanoi.history_group_size(index).unwrap_or_else(|| self.hanoi.group_size(to, from));
+        if !self.shift_group(to, from, count, None) { return }
+        // the cost is the one of the original move, from the position it was made in
+        self.moves = self.moves.saturating_sub(self.hanoi.group_move_cost(from, to, count));
+        self.undo_index = index;
+        // blindfolded players can't see the undo, so they're told what their board looks like now
+        if self.blindfold {
+            let disks = if count == 1 { "a disk".to_string() } else { format!("{count} disks") };
+            self.show_toast(format!("Undo: moved {disks} back from pole {} to pole {}", to + 1, from + 1));
+        }
+    }
+    /// Plays the last undone move again.
+    pub fn redo_move(&mut self) {
+        if self.spectating() || !matches!(self.state, GameState::Playing(_)) { return }
+        let index = self.undo_index;
+        let Some(&Move(_, from, to, _)) = self.hanoi.moves_history.get(index) else { return };
+        let count = self.hanoi.history_group_size(index).unwrap_or_else(|| self.hanoi.group_size(from, to));
+        let cost = self.hanoi.group_move_cost(from, to, count);
+        if !self.shift_group(from, to, count, None) { return }
+        self.moves += cost;
+        if let GameState::Playing(time) = self.state {
+            // the replay shows the move when it was played again
+            self.hanoi.moves_history[index].0 = time.elapsed();
+        }
+        self.undo_index += 1;
+    }
+    /// Every move replaces the animation of the previous one, which lands at once,
+    /// so that the animated disk is always the top one of its pole.
+    fn start_move_animation(&mut self, from: usize, to: usize, input: Option<InputSource>) {
+        // a dropped disk is already where it lands
+        let animated = !self.animation_duration.is_zero() && input != Some(InputSource::DragAndDrop);
+        self.move_animation = animated.then(|| (from, to, Instant::now(), self.animation_duration));
+    }
+    /// The pole a moved disk is gliding from and how far along it is, when it's gliding onto pole `i`.
+    pub fn move_animation_on(&self, i: usize) -> Option<(usize, f32)> {
+        let (from, to, start, duration) = self.move_animation?;
+        let elapsed = start.elapsed();
+        (to == i && elapsed < duration).then(|| (from, elapsed.as_secs_f32() / duration.as_secs_f32()))
+    }
+    pub fn update_hint(&mut self) {
+        self.hint = if self.show_hint { self.hanoi.next_optimal_move() } else { None };
+    }
+    pub fn record_last_move(&mut self, from: usize, to: usize) {
+        if let Some(&disk_number) = self.hanoi.poles[to].last() {
+            self.last_move = Some((from, disk_number, Instant::now()));
+        }
+    }
+    pub fn undo_count(&self) -> usize {
+        self.undo_index
+    }
+    pub fn redo_count(&self) -> usize {
+        self.hanoi.moves_history.len().saturating_sub(self.undo_index)
+    }
+    /// Drops the oldest moves once the history grows past `move_history_limit`,
+    /// keeping a rolling window of the most recent ones.
+    fn limit_history(&mut self) {
+        if let Some(limit) = self.move_history_limit {
+            let excess = self.hanoi.moves_history.len().saturating_sub(limit);
+            if excess > 0 {
+                self.hanoi.moves_history.drain(..excess);
+                self.hanoi.history_truncated = true;
+                self.undo_index = self.undo_index.saturating_sub(excess);
+            }
+        }
+    }
+    pub fn rematch(&mut self) {
+        if let Some(header) = self.last_header {
+            self.apply_header(header);
+            self.soft_reset();
+        }
+    }
+    /// Lets the bot solve the current settings from the start, or stops it and gives the game back.
+    pub fn toggle_bot(&mut self) {
+        let bot = self.player == PlayerKind::Bot;
+        self.soft_reset();
+        if !bot {
+            self.set_player(PlayerKind::Bot);
         }
     }
-    #[inline]
-    pub fn reset_undo(&mut self) {
-        self.undo_index = self.hanoi.moves_history.len();
+    /// Ends a game whose board was just solved, recording it like any game played by hand.
+    pub fn check_finished(&mut self, ctx: &egui::Context) {
+        match self.state {
+            GameState::Playing(start) if self.hanoi.finished() => {
+                let elapsed = start.elapsed();
+                self.state = GameState::Finished(elapsed);
+                // the undone moves left to redo aren't part of the run
+                self.hanoi.moves_history.truncate(self.undo_index);
+                // a clock that ran across resets doesn't time a single run
+                if self.retried != Some(ResetMode::Continuous) {
+                    self.save_score(elapsed);
+                    self.trainer_finish(elapsed);
+                }
+                self.sonify_finish();
+                if self.auto_share {
+                    self.copy_share_text(ctx, elapsed);
+                }
+            },
+            _ => {},
+        }
+    }
+    /// Puts the warmed up board back at the start, the next move starts the game for real.
+    pub fn end_warmup(&mut self) {
+        if !self.warming_up { return }
+        self.warming_up = false;
+        self.hanoi.reset();
+        self.last_move = None;
+        self.clear_play_state();
+    }
+    /// Drops anything a play mode was in the middle of, like a started drag or swift selection.
+    pub fn clear_play_state(&mut self) {
+        self.dragging_pole = None;
+        self.swift_pole = None;
+        (*HUMAN_PLAY).lock().iter_mut().for_each(|p| p.reset(self));
+    }
+    /// Every way of playing by hand is on until the player turns it off, including the ones added after the save was made.
+    pub fn is_human_play_enabled(&self, source: InputSource) -> bool {
+        self.human_plays.get(source.name()).copied().unwrap_or(true)
+    }
+    pub fn set_human_play_enabled(&mut self, source: InputSource, enabled: bool) {
+        self.human_plays.insert(source.name().to_string(), enabled);
+        self.clear_play_state();
+    }
+    pub fn set_player(&mut self, player: PlayerKind) {
+        // only the player warms up, the bots' moves count from the first one
+        if player != PlayerKind::Human {
+            self.warming_up = false;
+        }
+        self.player = player;
+        self.clear_play_state();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{hanoi::MAX_POLES, highscores::{Header, Move}, util::ResetMode, GameState, HanoiApp};
+
+    use super::{InputSource, PlayerKind};
+
+    fn mid_selection() -> HanoiApp {
+        HanoiApp {
+            dragging_pole: Some(0),
+            swift_pole: Some(1),
+            cycle_pole: Some(2),
+            ..Default::default()
+        }
+    }
+
+    fn assert_clean(app: &HanoiApp) {
+        assert_eq!(app.dragging_pole, None);
+        assert_eq!(app.swift_pole, None);
+        assert_eq!(app.cycle_pole, None);
+    }
+
+    #[test]
+    fn play_modes_stay_off_by_name() {
+        let mut app = HanoiApp::default();
+        assert!(InputSource::ALL.iter().all(|&source| app.is_human_play_enabled(source)));
+        app.swift_pole = Some(1);
+        app.set_human_play_enabled(InputSource::SwiftKeys, false);
+        assert_eq!(app.swift_pole, None);
+
+        // a play mode that was removed since the save was made, and one that was added
+        let mut saved = ron::from_str::<HashMap<String, bool>>(&ron::to_string(&app.human_plays).unwrap()).unwrap();
+        saved.insert("OldKeys".to_string(), false);
+        saved.remove(InputSource::QuickKeys.name());
+        app.human_plays = saved.into_iter().collect();
+        assert!(!app.is_human_play_enabled(InputSource::SwiftKeys));
+        assert!(app.is_human_play_enabled(InputSource::QuickKeys));
+        assert!(app.is_human_play_enabled(InputSource::DragAndDrop));
+    }
+
+    #[test]
+    fn switching_players_clears_play_state() {
+        let mut app = mid_selection();
+        app.set_player(PlayerKind::Bot);
+        assert_clean(&app);
+
+        let mut app = mid_selection();
+        app.load_replay(Header::default(), Default::default());
+        assert_clean(&app);
+
+        let mut app = mid_selection();
+        app.soft_reset();
+        assert_eq!(app.player, PlayerKind::Human);
+        assert_clean(&app);
+    }
+
+    #[test]
+    fn moves_to_poles_out_of_play_are_ignored() {
+        let mut app = HanoiApp {
+            reset_on_invalid_move: true,
+            ..Default::default()
+        };
+        app.full_move(0, app.hanoi.poles_count, None);
+        app.full_move(0, MAX_POLES, None);
+        assert_eq!(app.moves, 0);
+        assert_eq!(app.state, GameState::Reset);
+        assert!(app.hanoi.moves_history.is_empty());
+        assert_eq!(app.hanoi.poles[0].len(), app.hanoi.disks_count);
+    }
+
+    #[test]
+    fn bot_can_be_started_and_stopped() {
+        let mut app = HanoiApp::default();
+        app.toggle_bot();
+        assert_eq!(app.player, PlayerKind::Bot);
+        app.bot_play();
+        assert!(app.hanoi.finished());
+
+        app.toggle_bot();
+        assert_eq!(app.player, PlayerKind::Human);
+        assert_eq!(app.state, GameState::Reset);
+        assert_eq!(app.hanoi.poles[0].len(), app.hanoi.disks_count);
+    }
+
+    #[test]
+    fn undoing_a_multi_move_brings_back_the_same_group() {
+        let mut app = HanoiApp::default();
+        app.hanoi.disks_count = 5;
+        app.hanoi.multi_move = true;
+        app.hanoi.merge_start = true;
+        app.soft_reset();
+        for (from, to) in [(0, 1), (0, 2), (1, 2)] {
+            app.full_move(from, to, None);
+        }
+        assert_eq!(app.hanoi.poles[2].as_slice(), [5, 3, 2, 1]);
+        // disk 3 would fit back on disk 4 too
+        app.undo_move();
+        assert_eq!(app.hanoi.poles[1].as_slice(), [4, 2, 1]);
+        assert_eq!(app.hanoi.poles[2].as_slice(), [5, 3]);
+    }
+
+    #[test]
+    fn redo_plays_the_undone_moves_until_a_new_move() {
+        let mut app = HanoiApp::default();
+        for (from, to) in [(0, 2), (0, 1), (2, 1)] {
+            app.full_move(from, to, None);
+        }
+        let solved = app.hanoi.poles.clone();
+        app.undo_move();
+        app.undo_move();
+        assert_eq!((app.undo_count(), app.redo_count()), (1, 2));
+
+        app.redo_move();
+        app.redo_move();
+        app.redo_move();
+        assert_eq!(app.hanoi.poles, solved);
+        assert_eq!((app.undo_count(), app.redo_count()), (3, 0));
+
+        app.undo_move();
+        app.full_move(2, 0, None);
+        assert_eq!(app.redo_count(), 0, "the new move replaced the undone one");
+        let position = app.hanoi.poles.clone();
+        app.redo_move();
+        assert_eq!(app.hanoi.poles, position);
+    }
+
+    #[test]
+    fn undo_rewinds_the_counter_and_the_history() {
+        let mut app = HanoiApp::default();
+        for (from, to) in [(0, 2), (0, 1)] {
+            app.full_move(from, to, None);
+        }
+        app.undo_move();
+        assert_eq!(app.moves, 1);
+        let moves = |app: &HanoiApp| app.hanoi.moves_history[..app.undo_count()].iter().map(|&Move(_, from, to, _)| (from, to)).collect::<Vec<_>>();
+        assert_eq!(moves(&app), [(0, 2)]);
+
+        app.redo_move();
+        assert_eq!(app.moves, 2);
+        assert_eq!(app.hanoi.moves_history.len(), 2);
+        assert_eq!(moves(&app), [(0, 2), (0, 1)], "a redo is the same as never undoing");
+
+        app.undo_move();
+        app.undo_move();
+        app.full_move(0, 1, None);
+        assert_eq!(app.moves, 1);
+        assert_eq!(app.hanoi.moves_history.len(), 1, "the undone moves are gone for good");
+        assert_eq!(moves(&app), [(0, 1)]);
+    }
+
+    #[test]
+    fn blindfold_undo_is_announced() {
+        let mut app = HanoiApp::default();
+        app.full_move(0, 2, None);
+        app.undo_move();
+        assert_eq!(app.toast, None);
+
+        app.blindfold = true;
+        app.full_move(0, 2, None);
+        app.undo_move();
+        assert_eq!(app.toast.map(|(text, _)| text).as_deref(), Some("Undo: moved a disk back from pole 3 to pole 1"));
+    }
+
+    #[test]
+    fn warmup_moves_count_for_nothing() {
+        let mut app = HanoiApp {
+            warmup: true,
+            ..Default::default()
+        };
+        app.soft_reset();
+        app.full_move(0, 2, None);
+        assert_eq!(app.hanoi.poles[2].len(), 1);
+        assert_eq!((app.moves, app.state.clone()), (0, GameState::Reset));
+        assert!(app.hanoi.moves_history.is_empty());
+
+        app.end_warmup();
+        assert!(app.hanoi.poles[2].is_empty());
+        app.full_move(0, 2, None);
+        assert_eq!(app.moves, 1);
+        assert!(matches!(app.state, GameState::Playing(_)));
+
+        app.soft_reset();
+        assert!(app.warming_up, "every game starts with a warmup");
+        app.end_warmup();
+        app.full_move(0, 2, None);
+        app.reset_mode = ResetMode::BoardOnly;
+        app.retry();
+        assert!(!app.warming_up, "retries keeping the game don't warm up again");
+    }
+
+    #[test]
+    fn step_bot_skips_the_warmup() {
+        let mut app = HanoiApp {
+            warmup: true,
+            ..Default::default()
+        };
+        app.soft_reset();
+        app.toggle_step_bot();
+        assert!(!app.warming_up);
+        app.step_bot_advance();
+        assert_eq!(app.moves, 1);
+        assert!(matches!(app.state, GameState::Playing(_)));
+    }
+
+    #[test]
+    fn moves_glide_unless_dropped() {
+        let mut app = HanoiApp::default();
+        app.full_move(0, 2, Some(InputSource::QuickKeys));
+        assert_eq!(app.move_animation_on(2).map(|(from, _)| from), Some(0));
+        assert_eq!(app.move_animation_on(1), None);
+
+        app.full_move(0, 1, Some(InputSource::DragAndDrop));
+        assert_eq!(app.move_animation, None, "the previous animation ends too");
+
+        app.animation_duration = Duration::ZERO;
+        app.full_move(2, 1, None);
+        assert_eq!(app.move_animation, None);
     }
 }
@@ -1,30 +1,220 @@
 use std::time::Instant;
 
-use crate::{GameState, HanoiApp};
+use crate::{hanoi::RequiredMoves, play::PlayerKind, GameState, HanoiApp};
+
+/// The most moves the bot plays, every solution is computed in full before it starts so bigger ones would freeze the app.
+const BOT_MAX_MOVES: u128 = 1 << 24;
 
 impl HanoiApp {
+    /// The bot's solution, or the game given back to the player when there is nothing to solve,
+    /// when the solution would be too long to compute or when the bot doesn't know how to solve these settings.
+    fn bot_solution(&mut self) -> Option<Vec<(usize, usize)>> {
+        if !self.hanoi.is_valid_goal() {
+            self.soft_reset();
+            self.show_toast("The bot can't play with these start and end poles");
+            return None
+        }
+        let required_moves = self.hanoi.required_moves();
+        let too_many = match required_moves {
+            RequiredMoves::Count(moves) => moves > BOT_MAX_MOVES,
+            RequiredMoves::Overflow => true,
+            RequiredMoves::Unknown | RequiredMoves::Impossible => false,
+        };
+        if too_many {
+            self.soft_reset();
+            self.show_toast(format!("Too many moves for the bot ({required_moves}), it plays at most {BOT_MAX_MOVES}"));
+            return None
+        }
+        // the solver realizes the Frame-Stewart split for any number of poles, along with every variant it knows
+        let solution = self.hanoi.solve();
+        if solution.is_none() {
+            self.soft_reset();
+            self.show_toast("The bot doesn't know how to solve these settings");
+        }
+        solution
+    }
+
     pub fn bot_play(&mut self) {
         if self.state == GameState::Reset {
+            let Some(solution) = self.bot_solution() else { return };
             let start_time = Instant::now();
             self.state = GameState::Playing(start_time);
             self.moves = 0;
-            fn hanoi_bot(game: &mut HanoiApp, n: usize, from_rod: usize, to_rod: usize, aux_rod: usize) {
-                if n > 0 {
-                    hanoi_bot(game, n - 1, from_rod, aux_rod, to_rod);
-                    if game.hanoi.shift(from_rod, to_rod) {
-                        game.moves += 1;
-                    }
-                    hanoi_bot(game, n - 1, aux_rod, to_rod, from_rod);
+            for (from, to) in solution {
+                let cost = self.hanoi.move_cost(from, to);
+                if self.hanoi.shift(from, to) {
+                    self.moves += cost;
                 }
             }
-            hanoi_bot(
-                self,
-                self.hanoi.disks_count,
-                self.hanoi.start_pole - 1,
-                (self.hanoi.end_pole.unwrap_or(self.hanoi.start_pole)) % self.hanoi.poles_count,
-                (self.hanoi.end_pole.unwrap_or(self.hanoi.start_pole + 1)) % self.hanoi.poles_count,
-            );
+            debug_assert!(self.hanoi.finished(), "the bot's solution doesn't finish the game");
             self.state = GameState::Finished(start_time.elapsed());
         }
     }
+
+    /// Starts over with the bot's solution ready to be stepped through, or stops stepping through it.
+    pub fn toggle_step_bot(&mut self) {
+        let stepping = matches!(self.player, PlayerKind::StepBot(..));
+        self.soft_reset();
+        if stepping { return }
+        if let Some(moves) = self.bot_solution() {
+            self.set_player(PlayerKind::StepBot(moves, 0));
+        }
+    }
+
+    /// Plays the next move of the bot's solution, timed and recorded as if the player made it.
+    pub fn step_bot_advance(&mut self) {
+        let PlayerKind::StepBot(ref moves, ref mut index) = self.player else { return };
+        let Some(&(from, to)) = moves.get(*index) else { return };
+        *index += 1;
+        self.full_move(from, to, None);
+    }
+
+    /// How many of the bot's moves were played, out of all of them.
+    pub fn step_bot_progress(&self) -> Option<(usize, usize)> {
+        match &self.player {
+            PlayerKind::StepBot(moves, index) => Some((*index, moves.len())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{play::PlayerKind, GameState, HanoiApp};
+
+    fn bot_game(disks_count: usize, start_pole: usize, end_pole: Option<usize>) -> HanoiApp {
+        bot_game_on(3, disks_count, start_pole, end_pole)
+    }
+
+    fn bot_game_on(poles_count: usize, disks_count: usize, start_pole: usize, end_pole: Option<usize>) -> HanoiApp {
+        let mut app = HanoiApp::default();
+        app.hanoi.disks_count = disks_count;
+        app.hanoi.poles_count = poles_count;
+        app.hanoi.start_pole = start_pole;
+        app.hanoi.end_pole = end_pole;
+        app.soft_reset();
+        app.set_player(PlayerKind::Bot);
+        app.bot_play();
+        app
+    }
+
+    #[test]
+    fn bot_solves_to_every_end_pole() {
+        for disks_count in 1..=8 {
+            for start_pole in 1..=3 {
+                for end_pole in (1..=3).filter(|&end_pole| end_pole != start_pole) {
+                    let app = bot_game(disks_count, start_pole, Some(end_pole));
+                    assert!(app.hanoi.finished(), "{disks_count} disks, {start_pole} -> {end_pole}");
+                    assert_eq!(app.moves, app.hanoi.required_moves().to_number());
+                    assert_eq!(app.hanoi.poles[end_pole - 1].len(), disks_count);
+                    assert!((0..3).filter(|&pole| pole != end_pole - 1).all(|pole| app.hanoi.poles[pole].is_empty()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bot_finishes_on_pole_3_from_pole_2() {
+        let app = bot_game(4, 2, Some(3));
+        assert_eq!(app.hanoi.poles[2].as_slice(), [4, 3, 2, 1]);
+        assert_eq!(app.moves, 15);
+    }
+
+    #[test]
+    fn bot_solves_to_any_end_pole() {
+        for start_pole in 1..=3 {
+            let app = bot_game(5, start_pole, None);
+            assert!(app.hanoi.finished());
+            assert_eq!(app.moves, app.hanoi.required_moves().to_number());
+        }
+    }
+
+    #[test]
+    fn bot_solves_more_poles_optimally() {
+        for poles_count in 4..=6 {
+            for disks_count in 1..=12 {
+                for (start_pole, end_pole) in [(1, None), (2, Some(poles_count)), (poles_count, Some(1))] {
+                    let app = bot_game_on(poles_count, disks_count, start_pole, end_pole);
+                    assert!(app.hanoi.finished(), "{poles_count} poles, {disks_count} disks, {start_pole} -> {end_pole:?}");
+                    assert_eq!(app.moves, app.hanoi.required_moves().to_number());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bot_refuses_huge_towers() {
+        let mut app = HanoiApp::default();
+        app.hanoi.disks_count = 64;
+        app.soft_reset();
+        app.set_player(PlayerKind::Bot);
+        app.bot_play();
+        assert_eq!(app.player, PlayerKind::Human);
+        assert_eq!(app.state, GameState::Reset);
+        assert!(app.toast.is_some());
+    }
+
+    #[test]
+    fn bot_refuses_degenerate_goals() {
+        for (poles_count, end_pole) in [(3, 1), (2, 3)] {
+            let mut app = HanoiApp::default();
+            app.hanoi.end_pole = Some(end_pole);
+            app.hanoi.poles_count = poles_count;
+            app.soft_reset();
+            app.set_player(PlayerKind::Bot);
+            app.bot_play();
+            assert_eq!((app.player.clone(), app.state.clone(), app.moves), (PlayerKind::Human, GameState::Reset, 0));
+            assert!(app.toast.is_some());
+        }
+    }
+
+    #[test]
+    fn bot_refuses_settings_it_cant_solve() {
+        for (poles_count, multi_move) in [(4, false), (3, true)] {
+            let mut app = HanoiApp::default();
+            app.hanoi.poles_count = poles_count;
+            app.hanoi.disks_count = 4;
+            app.hanoi.merge_start = true;
+            app.hanoi.multi_move = multi_move;
+            app.soft_reset();
+            assert_eq!(app.hanoi.solve(), None, "{poles_count} poles, multi-move {multi_move}");
+            app.set_player(PlayerKind::Bot);
+            app.bot_play();
+            assert_eq!((app.player.clone(), app.state.clone(), app.moves), (PlayerKind::Human, GameState::Reset, 0));
+            assert!(app.toast.is_some());
+
+            app.toast = None;
+            app.toggle_step_bot();
+            assert_eq!(app.player, PlayerKind::Human);
+            assert!(app.toast.is_some());
+        }
+    }
+
+    #[test]
+    fn step_bot_plays_one_move_at_a_time() {
+        let mut app = HanoiApp::default();
+        app.hanoi.disks_count = 5;
+        app.soft_reset();
+        app.toggle_step_bot();
+        assert_eq!(app.step_bot_progress(), Some((0, 31)));
+        assert_eq!(app.state, GameState::Reset);
+
+        app.step_bot_advance();
+        assert_eq!(app.step_bot_progress(), Some((1, 31)));
+        assert_eq!(app.moves, 1);
+        assert!(matches!(app.state, GameState::Playing(_)));
+
+        (1..40).for_each(|_| app.step_bot_advance());
+        assert_eq!(app.step_bot_progress(), Some((31, 31)));
+        assert_eq!(app.moves, 31);
+        assert!(app.hanoi.finished());
+
+        app.check_finished(&Default::default());
+        assert!(matches!(app.state, GameState::Finished(_)));
+        assert_eq!(app.highscores[&app.get_current_header()].len(), 1, "the game is saved like a human one");
+
+        app.toggle_step_bot();
+        assert_eq!(app.player, PlayerKind::Human);
+        assert_eq!(app.hanoi.poles[0].len(), 5);
+    }
 }
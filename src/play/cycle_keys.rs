@@ -0,0 +1,65 @@
+use eframe::egui::Context;
+
+use crate::{GameState, HanoiApp};
+
+use super::{InputSource, Play};
+
+/// One key walks a cursor along the poles, another picks the pole under it:
+/// first the one to take a disk from, then the one to put it on.
+#[derive(Default)]
+pub struct CycleKeys {}
+
+impl Play for CycleKeys {
+    fn context_play(&mut self, app: &mut HanoiApp, ctx: &Context) {
+        if matches!(app.state, GameState::Finished(_)) || ctx.wants_keyboard_input() { return }
+        let (cycle, confirm) = ctx.input(|i| (i.key_pressed(app.cycle_key), i.key_pressed(app.confirm_key)));
+        if cycle {
+            app.cycle_pole = Some(app.cycle_pole.map_or(0, |pole| (pole + 1) % app.hanoi.poles_count));
+        }
+        if let Some(pole) = app.cycle_pole.filter(|_| confirm) {
+            app.swift_select(pole, InputSource::CycleKeys);
+        }
+    }
+    fn reset(&mut self, app: &mut HanoiApp) {
+        app.cycle_pole = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::{Context, Event, Key, RawInput};
+
+    use crate::{GameState, HanoiApp};
+
+    use super::{CycleKeys, Play};
+
+    fn press(app: &mut HanoiApp, key: Key) {
+        let input = RawInput {
+            events: vec![Event::Key { key, physical_key: None, pressed: true, repeat: false, modifiers: Default::default() }],
+            ..Default::default()
+        };
+        let _ = Context::default().run(input, |ctx| CycleKeys::default().context_play(app, ctx));
+    }
+
+    #[test]
+    fn cycling_picks_the_source_then_the_target() {
+        let mut app = HanoiApp::default();
+        let (cycle, confirm) = (app.cycle_key, app.confirm_key);
+        press(&mut app, confirm);
+        assert_eq!(app.swift_pole, None, "nothing is under the cursor yet");
+
+        press(&mut app, cycle);
+        press(&mut app, confirm);
+        assert_eq!((app.cycle_pole, app.swift_pole), (Some(0), Some(0)));
+        (0..5).for_each(|_| press(&mut app, cycle));
+        assert_eq!(app.cycle_pole, Some(2), "the cursor wraps around");
+        press(&mut app, confirm);
+        assert_eq!(app.swift_pole, None);
+        assert_eq!(app.hanoi.poles[2].as_slice(), [1]);
+        assert_eq!(app.moves, 1);
+
+        app.state = GameState::Finished(Default::default());
+        press(&mut app, cycle);
+        assert_eq!(app.cycle_pole, Some(2), "a finished game takes no input");
+    }
+}
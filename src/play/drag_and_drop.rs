@@ -2,7 +2,7 @@ use eframe::egui::{Pos2, Response};
 
 use crate::{GameState, HanoiApp, PolesVec};
 
-use super::{Play, PlayerKind};
+use super::{InputSource, Play, PlayerKind};
 
 #[derive(Default)]
 pub struct DragAndDrop {}
@@ -18,6 +18,7 @@ impl Play for DragAndDrop {
             None => {
                 poles.iter().enumerate().for_each(|(i, pole)| {
                     if pole.drag_started() {
+                        app.sound_pick();
                         app.dragging_pole = Some(i);
                     }
                 });
@@ -27,8 +28,7 @@ impl Play for DragAndDrop {
                     if let Some(pointer_position) = pointer_pos {
                         poles.iter().enumerate().for_each(|(to, pole)| {
                             if from != to && pole.rect.contains(pointer_position) {
-                                app.full_move(from, to);
-                                app.reset_undo();
+                                app.full_move(from, to, Some(InputSource::DragAndDrop));
                             }
                         });
                     }
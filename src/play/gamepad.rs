@@ -0,0 +1,222 @@
+use std::{collections::HashMap, fmt::Display, sync::{atomic::{AtomicUsize, Ordering}, mpsc::{channel, Receiver}}};
+
+use eframe::egui::Context;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use serde::{Deserialize, Serialize};
+
+use crate::{GameState, HanoiApp};
+
+use super::{InputSource, Play, PlayerKind};
+
+/// How many gamepads are connected, for the settings.
+static CONNECTED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn connected_gamepads() -> usize {
+    CONNECTED.load(Ordering::Relaxed)
+}
+
+/// A button, or a stick pushed one way past the deadzone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GamepadInput {
+    Button(Button),
+    Axis {
+        axis: Axis,
+        /// Which way the axis is pushed, left or down being negative.
+        negative: bool,
+    },
+}
+
+impl GamepadInput {
+    /// Bound to nothing, since unknown buttons are never pressed.
+    pub const UNBOUND: Self = Self::Button(Button::Unknown);
+}
+
+impl Display for GamepadInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::UNBOUND => write!(f, "Unbound"),
+            Self::Button(button) => write!(f, "{button:?}"),
+            Self::Axis { axis, negative: true } => write!(f, "{axis:?} −"),
+            Self::Axis { axis, negative: false } => write!(f, "{axis:?} +"),
+        }
+    }
+}
+
+/// What a gamepad input can be bound to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GamepadAction {
+    Pole(usize),
+    Undo,
+    Reset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    /// The input that picks every pole, the way swift keys do.
+    pub poles: Vec<GamepadInput>,
+    pub undo: GamepadInput,
+    pub reset: GamepadInput,
+    /// How far an axis has to be pushed to count as pressed, in percent.
+    pub deadzone: u8,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        use Button::*;
+        Self {
+            // the face buttons from the left one around, then the shoulders
+            poles: [West, North, East, South, LeftTrigger, RightTrigger].map(GamepadInput::Button).to_vec(),
+            undo: GamepadInput::Button(Select),
+            reset: GamepadInput::Button(Start),
+            deadzone: 50,
+        }
+    }
+}
+
+impl GamepadSettings {
+    pub fn bind(&mut self, action: GamepadAction, input: GamepadInput) {
+        match action {
+            GamepadAction::Pole(pole) => {
+                if let Some(bound) = self.poles.get_mut(pole) {
+                    *bound = input;
+                }
+            },
+            GamepadAction::Undo => self.undo = input,
+            GamepadAction::Reset => self.reset = input,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum GamepadEvent {
+    Button(Button),
+    /// The gamepad it comes from, and how far it's pushed from -1 to 1.
+    Axis(usize, Axis, f32),
+}
+
+/// Plays with gamepads next to the keyboard: a button per pole like swift keys, and buttons to undo and reset.
+#[derive(Default)]
+pub struct GamepadPlay {
+    /// The gamepad events, read on their own thread.
+    events: Option<Receiver<GamepadEvent>>,
+    /// Which way every axis of every gamepad is pushed past the deadzone, if it is.
+    axes: HashMap<(usize, Axis), Option<bool>>,
+}
+
+impl GamepadPlay {
+    /// Reads the gamepads with gilrs on a thread that wakes the game up on every event,
+    /// gamepads plugged in later included.
+    fn listen(ctx: &Context) -> Receiver<GamepadEvent> {
+        let (sender, receiver) = channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // without a gamepad interface the sender is dropped and no gamepad ever plays
+            let Ok(mut gilrs) = Gilrs::new() else { return };
+            CONNECTED.store(gilrs.gamepads().count(), Ordering::Relaxed);
+            loop {
+                let Some(event) = gilrs.next_event_blocking(None) else { continue };
+                let event = match event.event {
+                    EventType::ButtonPressed(button, _) if button != Button::Unknown => GamepadEvent::Button(button),
+                    EventType::AxisChanged(axis, value, _) if axis != Axis::Unknown => GamepadEvent::Axis(event.id.into(), axis, value),
+                    EventType::Connected | EventType::Disconnected => {
+                        CONNECTED.store(gilrs.gamepads().count(), Ordering::Relaxed);
+                        ctx.request_repaint();
+                        continue
+                    },
+                    _ => continue,
+                };
+                if sender.send(event).is_err() { return }
+                ctx.request_repaint();
+            }
+        });
+        receiver
+    }
+
+    /// The input that went down with `event`, an axis counting once when it's pushed past the deadzone.
+    fn pressed(&mut self, event: GamepadEvent, deadzone: f32) -> Option<GamepadInput> {
+        match event {
+            GamepadEvent::Button(button) => Some(GamepadInput::Button(button)),
+            GamepadEvent::Axis(gamepad, axis, value) => {
+                let direction = (value.abs() > deadzone).then_some(value < 0.0);
+                let previous = self.axes.insert((gamepad, axis), direction).flatten();
+                direction.filter(|&negative| previous != Some(negative)).map(|negative| GamepadInput::Axis { axis, negative })
+            },
+        }
+    }
+}
+
+impl Play for GamepadPlay {
+    fn context_play(&mut self, app: &mut HanoiApp, ctx: &Context) {
+        let deadzone = app.gamepad.deadzone as f32 / 100.0;
+        let events = self.events.get_or_insert_with(|| Self::listen(ctx)).try_iter().collect::<Vec<_>>();
+        for event in events {
+            let Some(input) = self.pressed(event, deadzone) else { continue };
+            match app.gamepad_binding.take() {
+                Some(action) => app.gamepad.bind(action, input),
+                None => app.gamepad_press(input),
+            }
+        }
+    }
+}
+
+impl HanoiApp {
+    fn gamepad_press(&mut self, input: GamepadInput) {
+        if input == self.gamepad.reset {
+            self.retry();
+        } else if input == self.gamepad.undo {
+            if matches!((&self.player, &self.state), (PlayerKind::Human, GameState::Playing(_))) {
+                self.undo_move();
+            }
+        } else if let Some(pole) = self.gamepad.poles.iter().take(self.hanoi.poles_count).position(|&bound| bound == input) {
+            self.swift_select(pole, InputSource::GamepadPlay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gilrs::{Axis, Button};
+
+    use crate::{GameState, HanoiApp};
+
+    use super::{GamepadAction, GamepadEvent, GamepadInput, GamepadPlay};
+
+    #[test]
+    fn axes_press_once_past_the_deadzone() {
+        let mut play = GamepadPlay::default();
+        assert_eq!(play.pressed(GamepadEvent::Button(Button::North), 0.5), Some(GamepadInput::Button(Button::North)));
+
+        let stick = |value| GamepadEvent::Axis(0, Axis::LeftStickX, value);
+        let pushed = |negative| Some(GamepadInput::Axis { axis: Axis::LeftStickX, negative });
+        assert_eq!(play.pressed(stick(-0.4), 0.5), None, "inside the deadzone");
+        assert_eq!(play.pressed(stick(-0.6), 0.5), pushed(true));
+        assert_eq!(play.pressed(stick(-0.9), 0.5), None, "still held");
+        assert_eq!(play.pressed(GamepadEvent::Axis(1, Axis::LeftStickX, -0.9), 0.5), pushed(true), "another gamepad");
+        assert_eq!(play.pressed(stick(0.8), 0.5), pushed(false));
+        assert_eq!(play.pressed(stick(0.0), 0.5), None);
+        assert_eq!(play.pressed(stick(0.8), 0.5), pushed(false));
+    }
+
+    #[test]
+    fn gamepads_play_like_swift_keys() {
+        let mut app = HanoiApp::default();
+        let [first, _, third, ..] = app.gamepad.poles[..] else { panic!("too few poles bound") };
+        app.gamepad_press(first);
+        app.gamepad_press(third);
+        assert_eq!(app.hanoi.poles[2].as_slice(), [1]);
+        assert!(matches!(app.state, GameState::Playing(_)));
+
+        app.gamepad_press(app.gamepad.undo);
+        assert_eq!(app.hanoi.poles[0].len(), app.hanoi.disks_count);
+        app.gamepad_press(first);
+        app.gamepad_press(third);
+        app.gamepad_press(app.gamepad.reset);
+        assert_eq!(app.state, GameState::Reset);
+
+        let stick = GamepadInput::Axis { axis: Axis::RightStickY, negative: true };
+        app.gamepad.bind(GamepadAction::Pole(0), stick);
+        app.gamepad_press(stick);
+        assert_eq!(app.swift_pole, Some(0));
+    }
+}
@@ -1,23 +1,202 @@
-use crate::GameState;
+use std::{fmt::Display, hash::Hash, path::Path};
 
-use super::{Play, PlayerKind};
+use eframe::egui::{InputState, Key, PointerButton};
+use serde::{de::{self, EnumAccess, IntoDeserializer, VariantAccess, Visitor}, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::hanoi::MAX_POLES;
+
+use super::{InputSource, Play};
 
 #[derive(Default)]
 pub struct QuickKeys {}
 
 impl Play for QuickKeys {
     fn context_play(&mut self, app: &mut crate::HanoiApp, ctx: &eframe::egui::Context) {
+        if ctx.wants_keyboard_input() { return }
         ctx.input(|i| {
             for qki in 0..app.quick_keys.len() {
-                let (key, from, to) = app.quick_keys[qki];
-                if i.key_pressed(key) {
-                    app.full_move(from - 1, to - 1);
-                    app.reset_undo();
+                let (input, from, to) = app.quick_keys[qki];
+                if input.pressed(i) {
+                    app.full_move(from - 1, to - 1, Some(InputSource::QuickKeys));
                 }
             }
-            if matches!((&app.player, &app.state), (PlayerKind::Human, GameState::Playing(_))) && i.key_pressed(app.undo_key) {
-                app.undo_move();
-            }
         });
     }
 }
+
+pub type QuickKey = (QuickInput, usize, usize);
+
+/// A keyboard key or a mouse button bound to a quick key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum QuickInput {
+    Key(Key),
+    Pointer(PointerButton),
+}
+
+const POINTER_BUTTONS: &[(PointerButton, &str)] = &[
+    (PointerButton::Primary, "MousePrimary"),
+    (PointerButton::Secondary, "MouseSecondary"),
+    (PointerButton::Middle, "MouseMiddle"),
+    (PointerButton::Extra1, "MouseExtra1"),
+    (PointerButton::Extra2, "MouseExtra2"),
+];
+
+impl QuickInput {
+    pub fn pressed(self, input: &InputState) -> bool {
+        match self {
+            QuickInput::Key(key) => input.key_pressed(key),
+            QuickInput::Pointer(button) => input.pointer.button_pressed(button),
+        }
+    }
+    pub fn down(self, input: &InputState) -> bool {
+        match self {
+            QuickInput::Key(key) => input.key_down(key),
+            QuickInput::Pointer(button) => input.pointer.button_down(button),
+        }
+    }
+    fn pointer_name(button: PointerButton) -> &'static str {
+        POINTER_BUTTONS.iter().find(|(b, _)| *b == button).map_or("MousePrimary", |(_, name)| name)
+    }
+}
+
+// `PointerButton` doesn't implement `Hash`
+impl Hash for QuickInput {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            QuickInput::Key(key) => key.hash(state),
+            QuickInput::Pointer(button) => Self::pointer_name(*button).hash(state),
+        }
+    }
+}
+
+impl From<Key> for QuickInput {
+    fn from(key: Key) -> Self {
+        QuickInput::Key(key)
+    }
+}
+
+impl Display for QuickInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuickInput::Key(key) => write!(f, "{key:?}"),
+            QuickInput::Pointer(button) => f.write_str(Self::pointer_name(*button)),
+        }
+    }
+}
+
+// Mouse buttons are written as extra `Key` variants, so that quick keys saved before they existed still load.
+impl Serialize for QuickInput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            QuickInput::Key(key) => key.serialize(serializer),
+            QuickInput::Pointer(button) => serializer.serialize_unit_variant("Key", 0, Self::pointer_name(*button)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for QuickInput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Identifier(String);
+        impl<'de> Deserialize<'de> for Identifier {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_identifier(IdentifierVisitor)
+            }
+        }
+        struct IdentifierVisitor;
+        impl Visitor<'_> for IdentifierVisitor {
+            type Value = Identifier;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a key or mouse button name")
+            }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Identifier, E> {
+                Ok(Identifier(value.to_string()))
+            }
+        }
+
+        struct QuickInputVisitor;
+        impl<'de> Visitor<'de> for QuickInputVisitor {
+            type Value = QuickInput;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a key or mouse button")
+            }
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<QuickInput, A::Error> {
+                let (Identifier(name), variant) = data.variant::<Identifier>()?;
+                variant.unit_variant()?;
+                if let Some((button, _)) = POINTER_BUTTONS.iter().find(|(_, n)| *n == name) {
+                    return Ok(QuickInput::Pointer(*button));
+                }
+                Key::deserialize(name.as_str().into_deserializer()).map(QuickInput::Key)
+            }
+        }
+
+        deserializer.deserialize_enum("Key", &[], QuickInputVisitor)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+pub fn load_quick_keys(path: &Path) -> Result<Vec<QuickKey>, String> {
+    let file = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let quick_keys: Vec<QuickKey> = if is_json(path) {
+        serde_json::from_str(&file).map_err(|err| err.to_string())?
+    } else {
+        ron::from_str(&file).map_err(|err| err.to_string())?
+    };
+
+    if let Some((key, from, to)) = quick_keys.iter().find(|&&(_, from, to)| {
+        from == to || !(1..=MAX_POLES).contains(&from) || !(1..=MAX_POLES).contains(&to)
+    }) {
+        return Err(format!("Invalid quick key {key}: {from} -> {to}"));
+    }
+
+    Ok(quick_keys)
+}
+
+/// The quick keys that move to or from a pole past `poles_count`, which do nothing in the current game.
+pub fn out_of_range_quick_keys(quick_keys: &[QuickKey], poles_count: usize) -> Vec<QuickKey> {
+    quick_keys.iter().copied().filter(|&(_, from, to)| from > poles_count || to > poles_count).collect()
+}
+
+pub fn save_quick_keys(path: &Path, quick_keys: &[QuickKey]) -> Result<(), String> {
+    let file = if is_json(path) {
+        serde_json::to_string_pretty(quick_keys).map_err(|err| err.to_string())?
+    } else {
+        ron::ser::to_string_pretty(quick_keys, Default::default()).map_err(|err| err.to_string())?
+    };
+    std::fs::write(path, file).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::{Key, PointerButton};
+
+    use super::{out_of_range_quick_keys, QuickInput, QuickKey};
+
+    #[test]
+    fn quick_keys_saved_as_keys_still_load() {
+        let old = ron::to_string(&vec![(Key::D, 1, 2), (Key::Space, 2, 3)]).unwrap();
+        let quick_keys: Vec<QuickKey> = ron::from_str(&old).unwrap();
+        assert_eq!(quick_keys, vec![(QuickInput::Key(Key::D), 1, 2), (QuickInput::Key(Key::Space), 2, 3)]);
+    }
+
+    #[test]
+    fn mouse_buttons_round_trip() {
+        let quick_keys: Vec<QuickKey> = vec![(QuickInput::Key(Key::F), 1, 3), (QuickInput::Pointer(PointerButton::Extra1), 3, 1)];
+
+        let ron = ron::to_string(&quick_keys).unwrap();
+        assert_eq!(ron::from_str::<Vec<QuickKey>>(&ron).unwrap(), quick_keys);
+
+        let json = serde_json::to_string(&quick_keys).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<QuickKey>>(&json).unwrap(), quick_keys);
+    }
+
+    #[test]
+    fn quick_keys_past_the_poles_in_play_are_reported() {
+        let quick_keys: Vec<QuickKey> = vec![(QuickInput::Key(Key::A), 1, 3), (QuickInput::Key(Key::B), 4, 1), (QuickInput::Key(Key::C), 2, 5)];
+        assert_eq!(out_of_range_quick_keys(&quick_keys, 3), quick_keys[1..]);
+        assert_eq!(out_of_range_quick_keys(&quick_keys, 4), quick_keys[2..]);
+        assert!(out_of_range_quick_keys(&quick_keys, 5).is_empty());
+    }
+}
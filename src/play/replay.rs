@@ -1,14 +1,53 @@
-use crate::{GameState, HanoiApp};
+use std::time::{Duration, Instant};
 
-use super::PlayerKind;
+use crate::{hanoi::HanoiGame, highscores::{Header, Move, Score}, GameState, HanoiApp};
+
+use super::{InputSource, PlayerKind};
+
+/// The longest a replayed disk takes to glide to its pole, shorter when the next move comes sooner.
+const REPLAY_GLIDE_DURATION: Duration = Duration::from_millis(150);
+/// How many moves on each side of a move its local pace is measured over, for adaptive replays.
+const ADAPTIVE_WINDOW: usize = 5;
+/// How long the input of a replayed move stays lit in the input display.
+const REPLAY_INPUT_FLASH: Duration = Duration::from_millis(150);
+
+/// The best run of the current settings, played back behind the live game to race against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ghost {
+    pub game: HanoiGame,
+    moves: Vec<Move>,
+    index: usize,
+}
 
 impl HanoiApp {
+    pub fn load_replay(&mut self, header: Header, score: Score) {
+        self.replay_schedule = if self.adaptive_replays { adaptive_schedule(&score.moves) } else { vec![] };
+        self.set_player(PlayerKind::Replay(score, 0));
+        self.replay_glide = None;
+        self.replay_paused_since = None;
+        self.moves = 0;
+        self.apply_header(header);
+        self.hanoi.reset();
+        self.completed_window = true;
+        self.state = GameState::Playing(Instant::now());
+    }
     pub fn replay_play(&mut self) {
+        let elapsed = match self.state {
+            GameState::Playing(start) => self.playing_elapsed(start),
+            _ => Duration::ZERO,
+        };
         if let PlayerKind::Replay(ref game, ref mut index) = self.player {
-            if let Some((time, from, to)) = game.moves.get(*index) {
-                if let GameState::Playing(start) = self.state {
-                    if start.elapsed() >= *time {
+            if let Some(Move(_, from, to, _)) = game.moves.get(*index) {
+                if let GameState::Playing(_) = self.state {
+                    let schedule = |i: usize| self.replay_schedule.get(i).or(game.moves.get(i).map(|Move(time, ..)| time)).copied();
+                    let time = schedule(*index).unwrap_or_default();
+                    if elapsed >= time {
                         self.hanoi.shift(*from, *to);
+                        self.last_move = self.hanoi.poles[*to].last().map(|&disk_number| (*from, disk_number, Instant::now()));
+                        if self.smooth_replays {
+                            let until_next = schedule(*index + 1).map_or(REPLAY_GLIDE_DURATION, |next| next.saturating_sub(time));
+                            self.replay_glide = Some((*from, *to, Instant::now(), until_next.min(REPLAY_GLIDE_DURATION)));
+                        }
                         *index += 1;
                         self.moves += 1;
                         if *index >= game.moves.len() {
@@ -19,4 +58,298 @@ impl HanoiApp {
             }
         }
     }
+    /// Jumps to the position after the first `target` moves of the replay, rebuilt from the start so that it can't drift,
+    /// with the clock set so that the playback goes on from there.
+    pub fn seek_replay(&mut self, target: usize) {
+        let PlayerKind::Replay(ref game, ref mut index) = self.player else { return };
+        let target = target.min(game.moves.len());
+        self.hanoi.reset();
+        for &Move(_, from, to, _) in &game.moves[..target] {
+            self.hanoi.shift(from, to);
+        }
+        *index = target;
+        self.moves = target as u128;
+        self.last_move = None;
+        self.replay_glide = None;
+
+        if target == game.moves.len() {
+            self.replay_paused_since = None;
+            self.state = GameState::Finished(game.time);
+            return;
+        }
+        let time = target.checked_sub(1)
+            .and_then(|last| self.replay_schedule.get(last).or(game.moves.get(last).map(|Move(time, ..)| time)))
+            .copied()
+            .unwrap_or_default();
+        let now = self.replay_paused_since.unwrap_or_else(Instant::now);
+        self.state = GameState::Playing(now.checked_sub(time).unwrap_or(now));
+    }
+    /// The input that made the last replayed move along with its poles, while it's still recent enough to be shown.
+    pub fn replay_input(&self) -> Option<(InputSource, usize, usize)> {
+        let PlayerKind::Replay(ref game, index) = self.player else { return None };
+        let &Move(time, from, to, input) = game.moves.get(index.checked_sub(1)?)?;
+        let time = self.replay_schedule.get(index - 1).copied().unwrap_or(time);
+        let GameState::Playing(start) = self.state else { return None };
+        (self.playing_elapsed(start).saturating_sub(time) < REPLAY_INPUT_FLASH).then_some((input?, from, to))
+    }
+    /// How long the game started at `start` has been going, without counting the time the replay spent paused.
+    pub fn playing_elapsed(&self, start: Instant) -> Duration {
+        match self.replay_paused_since {
+            Some(since) => since.saturating_duration_since(start),
+            None => start.elapsed(),
+        }
+    }
+    pub fn replay_paused(&self) -> bool {
+        self.replay_paused_since.is_some()
+    }
+    /// Freezes the clock of a replay in progress, or starts it again from where it stopped.
+    pub fn toggle_replay_pause(&mut self) {
+        let (PlayerKind::Replay(..), GameState::Playing(start)) = (&self.player, &self.state) else { return };
+        match self.replay_paused_since.take() {
+            // the start moves forward by the pause, so the moves that would have happened meanwhile still wait
+            Some(since) => self.state = GameState::Playing(*start + since.elapsed()),
+            None => self.replay_paused_since = Some(Instant::now()),
+        }
+    }
+    fn ghost_allowed(&self) -> bool {
+        self.ghost_enabled
+            && !self.blindfold
+            && self.player == PlayerKind::Human
+            && !self.warming_up
+            && self.hanoi.custom_start.is_none()
+    }
+    /// Starts the ghost of the best run along with the game, then moves it as the clock reaches its moves.
+    pub fn ghost_play(&mut self) {
+        if !self.ghost_allowed() || self.state == GameState::Reset {
+            self.ghost = None;
+            return;
+        }
+        // a finished game leaves the ghost where it was
+        let GameState::Playing(start) = self.state else { return };
+        if self.ghost.is_none() {
+            let header = self.get_current_header();
+            let Some(best) = self.highscores.get(&header).and_then(|scores| scores.first()).filter(|best| !best.truncated) else { return };
+            self.ghost = Some(Ghost { game: header.game(), moves: best.moves.clone(), index: 0 });
+        }
+        let elapsed = start.elapsed();
+        let Some(ghost) = &mut self.ghost else { return };
+        while let Some(&Move(_, from, to, _)) = ghost.moves.get(ghost.index).filter(|Move(time, ..)| *time <= elapsed) {
+            ghost.game.shift(from, to);
+            ghost.index += 1;
+        }
+    }
+    /// The pole a replayed disk is gliding from and how far along it is, when it's gliding onto pole `i`.
+    pub fn replay_glide_on(&self, i: usize) -> Option<(usize, f32)> {
+        let (from, to, start, duration) = self.replay_glide?;
+        let elapsed = start.elapsed();
+        (self.smooth_replays && matches!(self.player, PlayerKind::Replay(..)) && to == i && elapsed < duration)
+            .then(|| (from, elapsed.as_secs_f32() / duration.as_secs_f32()))
+    }
+}
+
+/// When every move is played back so that the replay goes at an even pace: every interval is stretched
+/// by how much denser the moves around it are than the average, slowing down the fast parts and speeding up the slow ones.
+pub fn adaptive_schedule(moves: &[Move]) -> Vec<Duration> {
+    let times = moves.iter().map(|Move(time, ..)| *time).collect::<Vec<Duration>>();
+    let (Some(&first), Some(&last)) = (times.first(), times.last()) else { return times };
+    if times.len() < 2 { return times }
+
+    let density = |count: usize, span: Duration| count as f64 / span.max(Duration::from_millis(1)).as_secs_f64();
+    let average_density = density(times.len() - 1, last - first);
+
+    let mut schedule = Vec::with_capacity(times.len());
+    schedule.push(first);
+    for i in 1..times.len() {
+        let (low, high) = (i.saturating_sub(ADAPTIVE_WINDOW), (i + ADAPTIVE_WINDOW).min(times.len() - 1));
+        let local_density = density(high - low, times[high] - times[low]);
+        let interval = times[i].saturating_sub(times[i - 1]).mul_f64(local_density / average_density);
+        schedule.push(schedule[i - 1] + interval);
+    }
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{highscores::{Header, Move, Score}, play::InputSource, GameState, HanoiApp};
+
+    use super::adaptive_schedule;
+
+    impl HanoiApp {
+        fn load_replay_from_moves(&mut self, header: Header, moves: Vec<Move>) {
+            let time = moves.last().map(|Move(time, ..)| *time).unwrap_or_default();
+            self.load_replay(header, Score { time, moves, ..Default::default() });
+        }
+    }
+
+    fn optimal_three_disks() -> Vec<Move> {
+        [(0, 2), (0, 1), (2, 1), (0, 2), (1, 0), (1, 2), (0, 2)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (from, to))| Move(Duration::from_millis(i as u64), from, to, None))
+            .collect()
+    }
+
+    fn play_until_finished(app: &mut HanoiApp) {
+        let timeout = Instant::now();
+        while !matches!(app.state, GameState::Finished(_)) {
+            assert!(timeout.elapsed() < Duration::from_secs(5), "replay did not finish");
+            app.replay_play();
+        }
+    }
+
+    #[test]
+    fn replay_reaches_finished() {
+        let header = Header { disks: 3, ..Default::default() };
+        let moves = optimal_three_disks();
+        let expected_time = moves.last().unwrap().0;
+
+        let mut app = HanoiApp::default();
+        app.load_replay_from_moves(header, moves);
+        play_until_finished(&mut app);
+
+        assert!(app.hanoi.finished());
+        assert_eq!(app.moves, 7);
+        assert_eq!(app.moves, app.hanoi.required_moves().to_number());
+        assert_eq!(app.state, GameState::Finished(expected_time));
+    }
+
+    #[test]
+    fn replay_applies_header() {
+        let header = Header { disks: 3, poles: 4, start_pole: 2, end_pole: Some(4), ..Default::default() };
+        let moves = vec![Move(Duration::ZERO, 1, 3, None)];
+
+        let mut app = HanoiApp::default();
+        app.load_replay_from_moves(header, moves);
+
+        assert_eq!(app.get_current_header(), header);
+        assert_eq!(app.hanoi.poles[1].len(), 3);
+    }
+
+    #[test]
+    fn glides_last_until_the_next_move() {
+        let header = Header { disks: 3, ..Default::default() };
+        let mut app = HanoiApp::default();
+        app.load_replay_from_moves(header, optimal_three_disks());
+        app.replay_play();
+        assert_eq!(app.replay_glide.map(|(from, to, _, duration)| (from, to, duration)), Some((0, 2, Duration::from_millis(1))));
+
+        app.smooth_replays = false;
+        app.load_replay_from_moves(header, optimal_three_disks());
+        app.replay_play();
+        assert_eq!(app.replay_glide, None);
+    }
+
+    #[test]
+    fn replay_inputs_follow_the_moves() {
+        let header = Header { disks: 3, ..Default::default() };
+        let moves = vec![
+            Move(Duration::ZERO, 0, 2, Some(InputSource::QuickKeys)),
+            Move(Duration::from_secs(60), 0, 1, Some(InputSource::SwiftKeys)),
+        ];
+        let mut app = HanoiApp::default();
+        app.load_replay_from_moves(header, moves);
+        assert_eq!(app.replay_input(), None);
+        app.replay_play();
+        assert_eq!(app.replay_input(), Some((InputSource::QuickKeys, 0, 2)));
+    }
+
+    #[test]
+    fn paused_replays_wait_for_the_resume() {
+        let header = Header { disks: 3, ..Default::default() };
+        let moves = optimal_three_disks().into_iter()
+            .map(|Move(time, from, to, input)| Move(time * 30, from, to, input))
+            .collect();
+        let mut app = HanoiApp::default();
+        app.load_replay_from_moves(header, moves);
+        app.toggle_replay_pause();
+        assert!(app.replay_paused());
+
+        std::thread::sleep(Duration::from_millis(100));
+        app.replay_play();
+        assert_eq!(app.moves, 1, "only the move at the start was due");
+        app.toggle_replay_pause();
+        app.replay_play();
+        assert!(app.moves < 3, "the moves due during the pause aren't skipped through");
+
+        play_until_finished(&mut app);
+        assert!(app.hanoi.finished());
+
+        app.load_replay_from_moves(header, optimal_three_disks());
+        app.toggle_replay_pause();
+        app.soft_reset();
+        assert!(!app.replay_paused());
+    }
+
+    #[test]
+    fn seeking_rebuilds_the_position() {
+        let header = Header { disks: 3, ..Default::default() };
+        let moves = optimal_three_disks();
+        let position_after = |count: usize| {
+            let mut game = header.game();
+            moves[..count].iter().for_each(|&Move(_, from, to, _)| { game.shift(from, to); });
+            game.poles
+        };
+
+        let mut app = HanoiApp::default();
+        app.load_replay_from_moves(header, moves.clone());
+        for target in [5, 2, 6, 0, 3] {
+            app.seek_replay(target);
+            assert_eq!(app.hanoi.poles, position_after(target), "after {target} moves");
+            assert_eq!(app.moves, target as u128);
+            assert!(matches!(app.state, GameState::Playing(_)));
+        }
+
+        app.seek_replay(moves.len());
+        assert!(app.hanoi.finished());
+        assert!(matches!(app.state, GameState::Finished(_)));
+        app.seek_replay(4);
+        play_until_finished(&mut app);
+        assert!(app.hanoi.finished());
+        assert_eq!(app.moves, moves.len() as u128);
+    }
+
+    #[test]
+    fn ghost_races_the_best_run() {
+        let header = Header { disks: 3, ..Default::default() };
+        let moves = optimal_three_disks().into_iter()
+            .map(|Move(time, from, to, input)| Move(time * 1000, from, to, input))
+            .collect::<Vec<_>>();
+        let mut app = HanoiApp { ghost_enabled: true, ..Default::default() };
+        app.apply_header(header);
+        app.soft_reset();
+        app.full_move(0, 1, None);
+        app.ghost_play();
+        assert_eq!(app.ghost, None, "no best run to race");
+
+        app.insert_score(header, Score { time: moves[6].0, moves, ..Default::default() });
+        app.ghost_play();
+        let ghost = app.ghost.clone().unwrap();
+        assert_eq!(ghost.game.poles[2].as_slice(), [1], "the ghost made its first move");
+        assert_eq!(app.hanoi.poles[1].as_slice(), [1], "apart from the live game");
+
+        app.blindfold = true;
+        app.ghost_play();
+        assert_eq!(app.ghost, None);
+        app.blindfold = false;
+        app.soft_reset();
+        app.ghost_play();
+        assert_eq!(app.ghost, None);
+    }
+
+    #[test]
+    fn adaptive_schedule_evens_out_the_pace() {
+        // a burst of 20 moves 10ms apart, then 20 moves a second apart
+        let times = (0..20).map(|i| i * 10).chain((1..=20).map(|i| 190 + i * 1000));
+        let moves = times.map(|ms| Move(Duration::from_millis(ms), 0, 1, None)).collect::<Vec<_>>();
+        let schedule = adaptive_schedule(&moves);
+        assert_eq!(schedule.len(), moves.len());
+        assert!(schedule.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let interval = |i: usize| (schedule[i + 1] - schedule[i]).as_secs_f64();
+        // deep inside each section the moves come at the same pace
+        assert!((interval(5) / interval(30) - 1.0).abs() < 0.01, "{} and {}", interval(5), interval(30));
+        assert!(adaptive_schedule(&moves[..1]) == [moves[0].0]);
+    }
 }
@@ -1,7 +1,8 @@
 use eframe::egui::Key;
 
-use super::Play;
+use super::{InputSource, Play};
 
+/// The default key of every pole.
 pub const SWIFT_KEYS: &[Key] = &[
     Key::Num1, Key::Num2, Key::Num3,
     Key::Num4, Key::Num5, Key::Num6,
@@ -13,17 +14,13 @@ pub struct SwiftKeys {}
 
 impl Play for SwiftKeys {
     fn context_play(&mut self, app: &mut crate::HanoiApp, ctx: &eframe::egui::Context) {
+        if ctx.wants_keyboard_input() { return }
+        let poles_count = app.hanoi.poles_count;
+        let keys = app.swift_keys.clone();
         ctx.input(|input| {
-            SWIFT_KEYS.iter().enumerate().for_each(|(i, k)| {
+            keys.iter().take(poles_count).enumerate().for_each(|(i, k)| {
                 if input.key_pressed(*k) {
-                    app.swift_pole = match app.swift_pole {
-                        None => Some(i),
-                        Some(from) => {
-                            app.full_move(from, i);
-                            app.reset_undo();
-                            None
-                        }
-                    }
+                    app.swift_select(i, InputSource::SwiftKeys);
                 }
             });
         });
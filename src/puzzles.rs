@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{hanoi::Board, highscores::Header, HanoiApp};
+
+/// A named position to practice from, along with the settings it was saved with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PuzzlePreset {
+    pub name: String,
+    pub header: Header,
+    pub board: Board,
+}
+
+impl PuzzlePreset {
+    pub fn validate(&self) -> Result<(), String> {
+        self.header.validate()?;
+        self.header.game().validate_board(&self.board)
+    }
+}
+
+impl HanoiApp {
+    /// Saves the current board under `name`, replacing the puzzle already called that way.
+    pub fn save_puzzle(&mut self, name: String) {
+        let preset = PuzzlePreset {
+            name,
+            header: self.get_current_header(),
+            board: self.hanoi.board(),
+        };
+        match self.puzzle_presets.iter_mut().find(|other| other.name == preset.name) {
+            Some(other) => *other = preset,
+            None => self.puzzle_presets.push(preset),
+        }
+    }
+
+    /// Starts practicing from the puzzle at `index`, every reset goes back to its position until `leave_puzzle`.
+    pub fn load_puzzle(&mut self, index: usize) -> Result<(), String> {
+        let preset = self.puzzle_presets.get(index).cloned().ok_or("No such puzzle")?;
+        preset.validate().map_err(|err| format!("Invalid puzzle {}: {err}", preset.name))?;
+        self.end_daily();
+        self.apply_header(preset.header);
+        self.scramble = false;
+        self.hanoi.custom_start = Some(preset.board);
+        self.soft_reset();
+        Ok(())
+    }
+
+    pub fn leave_puzzle(&mut self) {
+        self.end_daily();
+        self.scramble = false;
+        self.hanoi.custom_start = None;
+        self.soft_reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GameState, HanoiApp};
+
+    #[test]
+    fn puzzles_start_from_the_saved_board() {
+        let mut app = HanoiApp::default();
+        app.soft_reset();
+        app.full_move(0, 2, None);
+        app.full_move(0, 1, None);
+        let board = app.hanoi.board();
+        app.save_puzzle("two moves in".to_string());
+
+        app.soft_reset();
+        app.load_puzzle(0).unwrap();
+        assert_eq!(app.hanoi.board(), board);
+        assert_eq!(app.state, GameState::Reset);
+        assert_eq!(app.hanoi.required_moves().to_number(), 29);
+
+        app.full_move(2, 1, None);
+        app.soft_reset();
+        assert_eq!(app.hanoi.board(), board, "resets go back to the puzzle");
+
+        app.leave_puzzle();
+        assert_eq!(app.hanoi.poles[0].len(), app.hanoi.disks_count);
+    }
+
+    #[test]
+    fn invalid_puzzles_are_refused() {
+        let mut app = HanoiApp::default();
+        app.save_puzzle("broken".to_string());
+        app.puzzle_presets[0].board[0].swap(0, 1);
+        assert!(app.load_puzzle(0).is_err());
+        assert!(app.hanoi.custom_start.is_none());
+
+        app.puzzle_presets[0].board[0].swap(0, 1);
+        app.puzzle_presets[0].board[0].pop();
+        assert!(app.load_puzzle(0).is_err());
+    }
+}
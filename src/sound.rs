@@ -0,0 +1,255 @@
+use std::{io::Cursor, time::{Duration, Instant}};
+
+use eframe::egui;
+use rodio::{buffer::SamplesBuffer, Decoder, Source};
+
+use crate::{hanoi::MAX_POLES, GameState, HanoiApp, PlayerKind};
+
+mod output;
+
+pub const SAMPLE_RATE: u32 = 22050;
+/// Fade in and out of every tone, so that they don't click.
+const FADE: Duration = Duration::from_millis(5);
+/// The volume of the sonification cues.
+const VOLUME: f32 = 0.3;
+
+const CUE_DURATION: Duration = Duration::from_millis(90);
+const CUE_GAP: Duration = Duration::from_millis(30);
+const BASE_FREQUENCY: f32 = 261.63; // C4
+/// Semitones above `BASE_FREQUENCY` of every pole, a major pentatonic scale so that any two poles sound apart.
+const POLE_STEPS: [u8; MAX_POLES] = [0, 2, 4, 7, 9, 12, 14, 16, 19, 21, 24, 26, 28, 31, 33, 36];
+const LARGEST_DISK_FREQUENCY: f32 = 220.0;
+/// How many octaves above the largest disk the smallest one sounds.
+const DISK_OCTAVES: f32 = 3.0;
+const ERROR_FREQUENCY: f32 = 110.0;
+
+const PICK_SOUND: &[u8] = include_bytes!("../assets/sounds/pick.wav");
+const DROP_SOUND: &[u8] = include_bytes!("../assets/sounds/drop.wav");
+const ERROR_SOUND: &[u8] = include_bytes!("../assets/sounds/error.wav");
+
+const METRONOME_FREQUENCY: f32 = 1760.0;
+const METRONOME_DURATION: Duration = Duration::from_millis(20);
+pub const MIN_METRONOME_BPM: u32 = 30;
+pub const MAX_METRONOME_BPM: u32 = 600;
+pub const DEFAULT_METRONOME_BPM: u32 = 120;
+/// How far from the metronome period a move interval can be, as a fraction of the period, to be on beat.
+const METRONOME_TOLERANCE: f64 = 0.15;
+
+/// A sine tone, or silence when the frequency is zero.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tone {
+    pub frequency: f32,
+    pub duration: Duration,
+}
+
+impl Tone {
+    pub const fn new(frequency: f32, duration: Duration) -> Self {
+        Self { frequency, duration }
+    }
+
+    pub const fn silence(duration: Duration) -> Self {
+        Self::new(0.0, duration)
+    }
+
+    fn samples(self, volume: f32) -> impl Iterator<Item = i16> {
+        let count = (self.duration.as_secs_f32() * SAMPLE_RATE as f32) as usize;
+        let fade = (FADE.as_secs_f32() * SAMPLE_RATE as f32).max(1.0);
+        (0..count).map(move |i| {
+            let envelope = (i as f32 / fade).min((count - i) as f32 / fade).min(1.0);
+            let phase = std::f32::consts::TAU * self.frequency * i as f32 / SAMPLE_RATE as f32;
+            (phase.sin() * envelope * volume * i16::MAX as f32) as i16
+        })
+    }
+}
+
+/// The tones one after the other at `volume` (from 0 to 1).
+pub fn render(tones: &[Tone], volume: f32) -> Vec<i16> {
+    tones.iter().flat_map(|tone| tone.samples(volume)).collect()
+}
+
+pub use output::available as audio_available;
+
+/// Plays the tones without waiting for them, failing silently without an audio device.
+pub fn play(tones: Vec<Tone>, volume: f32) {
+    output::queue(SamplesBuffer::new(1, SAMPLE_RATE, render(&tones, volume)));
+}
+
+/// Plays an embedded sound file at `volume` (from 0 to 1), the same way as `play`.
+pub fn play_file(file: &'static [u8], volume: f32) {
+    if let Ok(decoder) = Decoder::new(Cursor::new(file)) {
+        output::queue(decoder.amplify(volume));
+    }
+}
+
+pub fn metronome_period(bpm: u32) -> Duration {
+    Duration::from_secs(60) / bpm.max(1)
+}
+
+/// Whether `interval` is close enough to the metronome period.
+pub fn on_beat(interval: Duration, period: Duration) -> bool {
+    interval.abs_diff(period).as_secs_f64() <= period.as_secs_f64() * METRONOME_TOLERANCE
+}
+
+pub fn pole_tone(pole: usize) -> Tone {
+    let steps = POLE_STEPS[pole.min(MAX_POLES - 1)];
+    Tone::new(BASE_FREQUENCY * 2f32.powf(steps as f32 / 12.0), CUE_DURATION)
+}
+
+/// Smaller disks sound higher.
+pub fn disk_tone(disk_number: usize, disks_count: usize) -> Tone {
+    let size = (disks_count - disk_number) as f32 / disks_count.max(2).saturating_sub(1) as f32;
+    Tone::new(LARGEST_DISK_FREQUENCY * 2f32.powf(size * DISK_OCTAVES), CUE_DURATION)
+}
+
+impl HanoiApp {
+    fn sonifying(&self) -> bool {
+        self.sonification && self.player == PlayerKind::Human
+    }
+
+    /// The pole that was selected, then the disk on top of it or a low tone when it's empty.
+    pub fn sonify_selection(&self, pole: usize) {
+        if !self.sonifying() { return }
+        let disk = match self.hanoi.poles[pole].last() {
+            Some(&disk_number) => disk_tone(disk_number, self.hanoi.disks_count),
+            None => Tone::new(ERROR_FREQUENCY, CUE_DURATION),
+        };
+        play(vec![pole_tone(pole), Tone::silence(CUE_GAP), disk], VOLUME);
+    }
+
+    /// The poles the disk left and reached then the disk, or a low tone when the move was refused.
+    pub fn sonify_move(&self, from: usize, to: usize, moved: bool) {
+        if !self.sonifying() { return }
+        let tones = match self.hanoi.poles[to].last() {
+            Some(&disk_number) if moved => vec![
+                pole_tone(from),
+                Tone::silence(CUE_GAP),
+                pole_tone(to),
+                Tone::silence(CUE_GAP),
+                disk_tone(disk_number, self.hanoi.disks_count),
+            ],
+            _ => vec![Tone::new(ERROR_FREQUENCY, CUE_DURATION * 2)],
+        };
+        play(tones, VOLUME);
+    }
+
+    /// Every pole from the first to the last, quickly.
+    pub fn sonify_finish(&self) {
+        if !self.sonifying() { return }
+        play((0..self.hanoi.poles_count).map(pole_tone).collect(), VOLUME);
+    }
+
+    /// The volume of the sound effects, clamped in case the save was edited by hand.
+    fn effects_volume(&self) -> f32 {
+        self.volume.clamp(0.0, 1.0)
+    }
+
+    fn sound_effects(&self) -> bool {
+        self.sound_enabled && self.player == PlayerKind::Human && self.effects_volume() > 0.0
+    }
+
+    fn play_effect(&self, file: &'static [u8]) {
+        if self.sound_effects() {
+            play_file(file, self.effects_volume());
+        }
+    }
+
+    /// A short high blip when a disk is taken from a pole.
+    pub fn sound_pick(&self) {
+        self.play_effect(PICK_SOUND);
+    }
+
+    /// A lower blip when a disk lands on a pole, or a buzz when the move wasn't allowed.
+    pub fn sound_drop(&self, moved: bool) {
+        self.play_effect(if moved { DROP_SOUND } else { ERROR_SOUND });
+    }
+
+    fn metronome_running(&self) -> bool {
+        self.metronome_bpm.is_some() && self.player == PlayerKind::Human && matches!(self.state, GameState::Playing(_))
+    }
+
+    /// Ticks when a beat is due while playing, and asks for a repaint in time for the next one.
+    pub fn metronome_update(&mut self, ctx: &egui::Context) {
+        let Some(bpm) = self.metronome_bpm.filter(|_| self.metronome_running()) else {
+            self.metronome_tick = None;
+            return
+        };
+        let period = metronome_period(bpm);
+        let now = Instant::now();
+        let next = match self.metronome_tick {
+            Some(tick) if now < tick + period => tick + period,
+            // keeps the beat steady instead of drifting by a frame every tick, unless a tick was missed
+            Some(tick) if now < tick + period * 2 => {
+                self.metronome_tick = Some(tick + period);
+                self.metronome_click();
+                tick + period * 2
+            },
+            _ => {
+                self.metronome_tick = Some(now);
+                self.metronome_click();
+                now + period
+            },
+        };
+        ctx.request_repaint_after(next.saturating_duration_since(now));
+    }
+
+    fn metronome_click(&self) {
+        play(vec![Tone::new(METRONOME_FREQUENCY, METRONOME_DURATION)], self.effects_volume());
+    }
+
+    /// Whether the time between the last two moves matched the metronome, while it's running.
+    pub fn metronome_on_beat(&self) -> Option<bool> {
+        if !self.metronome_running() { return None }
+        let period = metronome_period(self.metronome_bpm?);
+        match self.hanoi.moves_history.as_slice() {
+            [.., before, last] => Some(on_beat(last.0.saturating_sub(before.0), period)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use rodio::{Decoder, Source};
+
+    use super::{disk_tone, metronome_period, on_beat, render, Tone, DROP_SOUND, ERROR_SOUND, PICK_SOUND, SAMPLE_RATE};
+
+    #[test]
+    fn tones_hold_every_sample() {
+        let tones = [Tone::new(440.0, Duration::from_millis(100)), Tone::silence(Duration::from_millis(50))];
+        let samples = render(&tones, 0.3);
+        assert_eq!(samples.len(), (SAMPLE_RATE as usize / 10) + (SAMPLE_RATE as usize / 20));
+        assert!(samples.iter().any(|&sample| sample != 0));
+        // the silence stays silent
+        assert!(samples[samples.len() - 10..].iter().all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn embedded_sounds_decode() {
+        for file in [PICK_SOUND, DROP_SOUND, ERROR_SOUND] {
+            let decoder = Decoder::new(Cursor::new(file)).expect("a wav file");
+            assert_eq!((decoder.channels(), decoder.sample_rate()), (1, SAMPLE_RATE));
+            assert!(decoder.count() > 0);
+        }
+    }
+
+    #[test]
+    fn smaller_disks_sound_higher() {
+        for disks_count in 1..=64 {
+            let frequencies = (1..=disks_count).map(|disk| disk_tone(disk, disks_count).frequency).collect::<Vec<_>>();
+            assert!(frequencies.windows(2).all(|pair| pair[0] > pair[1]), "{disks_count} disks");
+        }
+    }
+
+    #[test]
+    fn moves_close_to_the_period_are_on_beat() {
+        let period = metronome_period(120);
+        assert_eq!(period, Duration::from_millis(500));
+        assert!(on_beat(period, period));
+        assert!(on_beat(Duration::from_millis(450), period));
+        assert!(on_beat(Duration::from_millis(570), period));
+        assert!(!on_beat(Duration::from_millis(400), period));
+        assert!(!on_beat(Duration::from_millis(600), period));
+    }
+}
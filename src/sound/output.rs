@@ -0,0 +1,37 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, mpsc::{channel, Sender}};
+
+use once_cell::sync::Lazy;
+use rodio::{OutputStream, Source};
+
+type Sound = Box<dyn Source<Item = f32> + Send>;
+
+/// Whether opening the output stream failed, in which case every sound is skipped.
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// The sounds waiting to be played, by the thread that keeps the output stream open.
+static OUTPUT: Lazy<Sender<Sound>> = Lazy::new(|| {
+    let (sender, receiver) = channel::<Sound>();
+    std::thread::spawn(move || {
+        // without an audio device the receiver is dropped and the sounds are thrown away
+        let Ok((_stream, handle)) = OutputStream::try_default() else {
+            UNAVAILABLE.store(true, Ordering::Relaxed);
+            return
+        };
+        for sound in receiver {
+            // the stream mixes the sounds that overlap
+            let _ = handle.play_raw(sound);
+        }
+    });
+    sender
+});
+
+/// Whether sounds can be heard, opening the output stream the first time it's asked.
+pub fn available() -> bool {
+    Lazy::force(&OUTPUT);
+    !UNAVAILABLE.load(Ordering::Relaxed)
+}
+
+/// Queues a sound, without waiting for it to be played.
+pub fn queue(sound: impl Source<Item = i16> + Send + 'static) {
+    let _ = OUTPUT.send(Box::new(sound.convert_samples()));
+}
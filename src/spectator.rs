@@ -0,0 +1,243 @@
+use std::{io::{BufRead, BufReader, Write}, net::{SocketAddr, TcpListener, TcpStream}, sync::Arc, time::{Duration, Instant}};
+
+use arrayvec::ArrayVec;
+use eframe::egui::{mutex::Mutex, Color32, Ui};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::Cli, hanoi::{HanoiGame, MAX_DISKS, MAX_POLES}, GameState, HanoiApp};
+
+pub static SPECTATOR: Lazy<Mutex<Option<Spectator>>> = Lazy::new(Default::default);
+
+/// What spectators receive, one JSON object per line, every time the board changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorFrame {
+    pub poles: Vec<Vec<usize>>,
+    pub disks_count: usize,
+    pub moves: u128,
+    pub state: SpectatorState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpectatorState {
+    Reset,
+    /// The time elapsed when the frame was sent, the viewer keeps the timer running from there.
+    Playing(Duration),
+    Finished(Duration),
+}
+
+impl SpectatorFrame {
+    /// Whether the frames only differ by the time elapsed in the game.
+    fn same_board(&self, other: &Self) -> bool {
+        self.poles == other.poles
+            && self.disks_count == other.disks_count
+            && self.moves == other.moves
+            && match (&self.state, &other.state) {
+                (SpectatorState::Playing(_), SpectatorState::Playing(_)) => true,
+                (a, b) => a == b,
+            }
+    }
+    fn is_valid(&self) -> bool {
+        (2..=MAX_POLES).contains(&self.poles.len())
+            && (1..=MAX_DISKS).contains(&self.disks_count)
+            && self.poles.iter().map(Vec::len).sum::<usize>() == self.disks_count
+            && self.poles.iter().flatten().all(|disk| (1..=self.disks_count).contains(disk))
+    }
+}
+
+pub enum Spectator {
+    Host {
+        listener: TcpListener,
+        clients: Vec<TcpStream>,
+        last_frame: Option<SpectatorFrame>,
+    },
+    Viewer {
+        frame: Arc<Mutex<Option<(SpectatorFrame, Instant)>>>,
+        error: Arc<Mutex<Option<String>>>,
+    },
+}
+
+impl Spectator {
+    pub fn host(address: SocketAddr) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Spectator::Host { listener, clients: vec![], last_frame: None })
+    }
+
+    pub fn connect(address: SocketAddr) -> std::io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        let frame = Arc::new(Mutex::new(None));
+        let error = Arc::new(Mutex::new(None));
+
+        let (thread_frame, thread_error) = (frame.clone(), error.clone());
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let received = line
+                    .map_err(|err| err.to_string())
+                    .and_then(|line| serde_json::from_str::<SpectatorFrame>(&line).map_err(|err| err.to_string()));
+                match received {
+                    Ok(received) => *thread_frame.lock() = Some((received, Instant::now())),
+                    Err(err) => {
+                        *thread_error.lock() = Some(err);
+                        return;
+                    },
+                }
+            }
+            *thread_error.lock() = Some("The host closed the connection".to_string());
+        });
+
+        Ok(Spectator::Viewer { frame, error })
+    }
+}
+
+/// Hosts or joins a spectated game, depending on the command line.
+pub fn start_spectator(cli: &Cli) {
+    let spectator = match (cli.host_spectators, cli.spectate) {
+        (Some(address), _) => Spectator::host(address),
+        (_, Some(address)) => Spectator::connect(address),
+        (None, None) => return,
+    };
+    match spectator {
+        Ok(spectator) => *SPECTATOR.lock() = Some(spectator),
+        Err(err) => eprintln!("Unable to start spectating: {err}"),
+    }
+}
+
+impl HanoiApp {
+    /// Sends the board to the spectators when hosting, or replaces it with the watched one.
+    pub fn spectator_update(&mut self) {
+        let mut spectator = SPECTATOR.lock();
+        match spectator.as_mut() {
+            Some(Spectator::Host { listener, clients, last_frame }) => {
+                while let Ok((mut client, _)) = listener.accept() {
+                    // a spectator that stops reading gets dropped instead of freezing the game
+                    if client.set_nonblocking(true).is_err() { continue }
+                    if last_frame.as_ref().is_none_or(|last_frame| send_frame(&mut client, last_frame)) {
+                        clients.push(client);
+                    }
+                }
+                let frame = self.spectator_frame();
+                if last_frame.as_ref().is_none_or(|last_frame| !last_frame.same_board(&frame)) {
+                    clients.retain_mut(|client| send_frame(client, &frame));
+                    *last_frame = Some(frame);
+                }
+            },
+            Some(Spectator::Viewer { frame, .. }) => {
+                let received = frame.lock().clone();
+                if let Some((frame, received)) = received.filter(|(frame, _)| frame.is_valid()) {
+                    self.apply_spectator_frame(frame, received);
+                }
+            },
+            None => {},
+        }
+    }
+
+    fn spectator_frame(&self) -> SpectatorFrame {
+        SpectatorFrame {
+            poles: self.hanoi.poles[..self.hanoi.poles_count].iter().map(|pole| pole.to_vec()).collect(),
+            disks_count: self.hanoi.disks_count,
+            moves: self.moves,
+            state: match self.state {
+                GameState::Reset => SpectatorState::Reset,
+                GameState::Playing(start) => SpectatorState::Playing(start.elapsed()),
+                GameState::Finished(time) => SpectatorState::Finished(time),
+            },
+        }
+    }
+
+    /// Shows the watched board in place of the player's own game, which is kept aside to be saved.
+    fn apply_spectator_frame(&mut self, frame: SpectatorFrame, received: Instant) {
+        if self.before_spectating.is_none() {
+            self.before_spectating = Some(self.hanoi.restarted());
+        }
+        // a plain game with the host's size, so that none of the player's own poles or variants point past the board
+        self.hanoi = HanoiGame {
+            poles_count: frame.poles.len(),
+            disks_count: frame.disks_count,
+            ..HanoiGame::new()
+        };
+        for (i, pole) in self.hanoi.poles.iter_mut().enumerate() {
+            *pole = frame.poles.get(i).into_iter().flatten().copied().collect::<ArrayVec<usize, MAX_DISKS>>();
+        }
+        self.moves = frame.moves;
+        self.state = match frame.state {
+            SpectatorState::Reset => GameState::Reset,
+            SpectatorState::Playing(elapsed) => GameState::Playing(received.checked_sub(elapsed).unwrap_or(received)),
+            SpectatorState::Finished(time) => GameState::Finished(time),
+        };
+    }
+
+    pub fn spectating(&self) -> bool {
+        matches!(*SPECTATOR.lock(), Some(Spectator::Viewer { .. }))
+    }
+
+    pub fn draw_spectator_status(&self, ui: &mut Ui) {
+        match &*SPECTATOR.lock() {
+            Some(Spectator::Host { clients, .. }) => {
+                ui.label(format!("👁 {} spectators", clients.len()));
+            },
+            Some(Spectator::Viewer { error, .. }) => match &*error.lock() {
+                Some(err) => { ui.colored_label(Color32::RED, format!("👁 {err}")); },
+                None => { ui.label("👁 Spectating"); },
+            },
+            None => {},
+        }
+    }
+}
+
+/// Whether the whole frame got sent, a client whose buffer is full fails with `WouldBlock` and is left with half a frame.
+fn send_frame(client: &mut TcpStream, frame: &SpectatorFrame) -> bool {
+    let Ok(mut line) = serde_json::to_string(frame) else { return false };
+    line.push('\n');
+    client.write_all(line.as_bytes()).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::{TcpListener, TcpStream}, time::Instant};
+
+    use crate::HanoiApp;
+
+    use super::{send_frame, SpectatorFrame, SpectatorState};
+
+    #[test]
+    fn frames_reproduce_the_board() {
+        let mut host = HanoiApp::default();
+        host.hanoi.poles_count = 4;
+        host.hanoi.disks_count = 6;
+        host.soft_reset();
+        host.full_move(0, 3, None);
+        host.full_move(0, 1, None);
+
+        let frame = host.spectator_frame();
+        let line = serde_json::to_string(&frame).unwrap();
+        let received = serde_json::from_str::<SpectatorFrame>(&line).unwrap();
+        assert!(received.is_valid());
+        assert!(received.same_board(&host.spectator_frame()));
+
+        let mut viewer = HanoiApp::default();
+        viewer.hanoi.poles_count = 8;
+        viewer.hanoi.start_pole = 8;
+        viewer.hanoi.end_pole = Some(7);
+        viewer.soft_reset();
+        viewer.apply_spectator_frame(received, Instant::now());
+        assert_eq!(viewer.hanoi.poles, host.hanoi.poles);
+        assert_eq!(viewer.moves, host.moves);
+        assert!(viewer.spectator_frame().same_board(&frame));
+        assert!(!viewer.hanoi.finished(), "the viewer's poles don't leak into the watched game");
+
+        let saved = viewer.saved();
+        assert_eq!((saved.hanoi.poles_count, saved.hanoi.start_pole, saved.hanoi.end_pole), (8, 8, Some(7)), "the viewer's own settings are saved");
+    }
+
+    #[test]
+    fn spectators_that_dont_read_get_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _spectator = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut client, _) = listener.accept().unwrap();
+        client.set_nonblocking(true).unwrap();
+
+        let frame = SpectatorFrame { poles: vec![vec![1; 100_000]], disks_count: 1, moves: 0, state: SpectatorState::Reset };
+        assert!((0..10_000).any(|_| !send_frame(&mut client, &frame)), "the full buffer fails instead of blocking");
+    }
+}
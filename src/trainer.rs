@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{hanoi::RequiredMoves, HanoiApp, PlayerKind};
+
+/// Walks the player up one disk at a time, every run finished under the target time unlocks the next level.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Trainer {
+    pub enabled: bool,
+    pub first_level: usize,
+    pub last_level: usize,
+    /// The target time of a level is this pace times the optimal move count.
+    pub time_per_move: Duration,
+    /// The disks count being practiced.
+    pub level: usize,
+    /// Runs finished at the current level, successful or not.
+    pub runs: usize,
+}
+
+impl Default for Trainer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            first_level: 3,
+            last_level: 15,
+            time_per_move: Duration::from_millis(250),
+            level: 3,
+            runs: 0,
+        }
+    }
+}
+
+impl Trainer {
+    pub fn start(&mut self) {
+        self.level = self.first_level;
+        self.runs = 0;
+    }
+
+    pub fn target(&self, required_moves: RequiredMoves) -> Option<Duration> {
+        match required_moves {
+            RequiredMoves::Count(moves) => u32::try_from(moves).ok().and_then(|moves| self.time_per_move.checked_mul(moves)),
+            _ => None,
+        }
+    }
+
+    pub fn at_last_level(&self) -> bool {
+        self.level >= self.last_level
+    }
+}
+
+impl HanoiApp {
+    /// Called when the player finishes a run, the next level starts with the next reset.
+    pub fn trainer_finish(&mut self, time: Duration) {
+        if !self.trainer.enabled || self.player != PlayerKind::Human || self.hanoi.disks_count != self.trainer.level {
+            return
+        }
+
+        self.trainer.runs += 1;
+        let Some(target) = self.trainer.target(self.hanoi.required_moves()) else { return };
+        if time > target { return }
+
+        if self.trainer.at_last_level() {
+            self.show_toast("Trainer complete!");
+        } else {
+            self.trainer.level += 1;
+            self.trainer.runs = 0;
+            self.show_toast(format!("Level up: {} disks", self.trainer.level));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::HanoiApp;
+
+    fn trained_app() -> HanoiApp {
+        let mut app = HanoiApp::default();
+        app.trainer.enabled = true;
+        app.trainer.first_level = 3;
+        app.trainer.last_level = 4;
+        app.trainer.start();
+        app.soft_reset();
+        app
+    }
+
+    #[test]
+    fn advances_only_under_the_target() {
+        let mut app = trained_app();
+        assert_eq!(app.hanoi.disks_count, 3);
+        // 7 moves at 250ms per move
+        app.trainer_finish(Duration::from_millis(1751));
+        assert_eq!((app.trainer.level, app.trainer.runs), (3, 1));
+
+        app.trainer_finish(Duration::from_millis(1750));
+        assert_eq!((app.trainer.level, app.trainer.runs), (4, 0));
+        app.soft_reset();
+        assert_eq!(app.hanoi.disks_count, 4);
+
+        app.trainer_finish(Duration::ZERO);
+        assert_eq!((app.trainer.level, app.trainer.runs), (4, 1), "the last level doesn't advance");
+    }
+
+    #[test]
+    fn ignores_runs_at_other_levels() {
+        let mut app = trained_app();
+        app.hanoi.disks_count = 5;
+        app.trainer_finish(Duration::ZERO);
+        assert_eq!((app.trainer.level, app.trainer.runs), (3, 0));
+    }
+}
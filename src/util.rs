@@ -1,14 +1,88 @@
-use eframe::egui::{self, Context, Key, Modifiers, Pos2};
+use std::{borrow::Cow, time::Duration};
 
-use crate::{play::{PlayerKind, HUMAN_PLAY}, GameState, HanoiApp};
+use eframe::egui::{self, Context, Key, Modifiers, Pos2, ThemePreference};
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+use crate::{hanoi::HanoiGame, highscores::Move, play::{InputSource, PlayerKind, QuickKey, SWIFT_KEYS}, GameState, HanoiApp};
+
+pub const DEFAULT_MOVE_HISTORY_LIMIT: usize = 1_000_000;
+
+/// What the reset key and button keep from the game they restart.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
+pub enum ResetMode {
+    /// Starts a brand new game.
+    #[default]
+    Full,
+    /// Puts the disks back without counting a new attempt.
+    BoardOnly,
+    /// Puts the disks back while the clock keeps running, to time a whole practice session.
+    Continuous,
+}
 
 impl HanoiApp {
     pub fn soft_reset(&mut self) {
+        self.reset(ResetMode::Full);
+    }
+    /// Restarts the game the way the player picked, for the reset key and button.
+    pub fn retry(&mut self) {
+        self.reset(self.reset_mode);
+    }
+    fn reset(&mut self, mode: ResetMode) {
+        // only a running clock can keep running
+        let mode = match (mode, &self.state) {
+            (ResetMode::Continuous, GameState::Playing(_)) | (ResetMode::BoardOnly, _) => mode,
+            _ => ResetMode::Full,
+        };
+        if self.trainer.enabled {
+            self.hanoi.disks_count = self.trainer.level;
+        }
+        self.apply_daily();
+        if self.scramble {
+            // a retry deals the same position again, the daily challenge brings its own seed
+            if mode == ResetMode::Full && !self.fixed_seed && self.daily.is_none() {
+                self.scramble_seed = fastrand::u64(..);
+            }
+            self.hanoi.scramble(self.scramble_seed);
+        }
         self.hanoi.reset();
-        self.state = GameState::Reset;
-        self.player = PlayerKind::Human;
+        if mode != ResetMode::Continuous {
+            self.state = GameState::Reset;
+        }
+        self.retried = (mode != ResetMode::Full).then_some(mode);
         self.moves = 0;
-        (*HUMAN_PLAY).lock().iter_mut().for_each(|p| p.reset(self));
+        self.undo_index = 0;
+        self.completed_window = true;
+        self.last_move = None;
+        self.replay_glide = None;
+        self.replay_paused_since = None;
+        self.move_animation = None;
+        self.metronome_tick = None;
+        // a retry keeps going from the game it restarts, without a new warmup
+        self.warming_up = self.warmup && mode == ResetMode::Full;
+        self.shuffle_color_seed();
+        self.update_hint();
+        self.set_player(PlayerKind::Human);
+    }
+
+    /// The app as it gets saved, with the settings that the daily challenge or a watched game replaced put back.
+    pub fn saved(&self) -> Cow<'_, Self> {
+        if self.before_daily.is_none() && self.before_spectating.is_none() {
+            return Cow::Borrowed(self)
+        }
+        let mut app = self.clone();
+        if let Some(hanoi) = &self.before_spectating {
+            app.hanoi = hanoi.clone();
+        }
+        if let Some(before) = &self.before_daily {
+            before.restore(&mut app);
+        }
+        Cow::Owned(app)
+    }
+
+    /// Picks new disk colors for the next game when shuffling them.
+    pub fn shuffle_color_seed(&mut self) {
+        self.color_seed = self.shuffle_colors.then(|| fastrand::u64(..));
     }
 
     pub fn equal_settings(&self, other: &Self) -> bool {
@@ -18,6 +92,7 @@ impl HanoiApp {
             && self.hanoi.poles_count == other.hanoi.poles_count
             && self.hanoi.start_pole == other.hanoi.start_pole
             && self.blindfold == other.blindfold
+            && self.outline == other.outline
             && self.show_poles == other.show_poles
             && self.disk_number == other.disk_number
             && self.player == other.player
@@ -38,12 +113,29 @@ impl HanoiApp {
 pub const fn truthy() -> bool { true }
 pub const fn falsy() -> bool { false }
 
+pub const fn move_history_limit() -> Option<usize> { Some(DEFAULT_MOVE_HISTORY_LIMIT) }
+
+pub const fn app_theme() -> ThemePreference { ThemePreference::System }
+
 pub const fn reset_key() -> Key { Key::R }
 pub const fn undo_key() -> Key { Key::Z }
+pub const fn redo_key() -> Key { Key::Y }
+pub const fn rematch_key() -> Key { Key::T }
+pub const fn disk_number_key() -> Key { Key::N }
+pub const fn bot_key() -> Key { Key::B }
+pub const fn begin_key() -> Key { Key::Enter }
+pub const fn step_key() -> Key { Key::ArrowRight }
+pub const fn pause_key() -> Key { Key::Space }
+pub const fn cycle_key() -> Key { Key::C }
+pub const fn confirm_key() -> Key { Key::V }
+pub fn swift_keys() -> Vec<Key> { SWIFT_KEYS.to_vec() }
+pub const fn graph_height() -> u32 { 128 }
+pub const fn volume() -> f32 { 0.5 }
+pub const fn animation_duration() -> Duration { Duration::from_millis(120) }
 
-pub fn quick_keys() -> Vec<(Key, usize, usize)> {
+pub fn quick_keys() -> Vec<QuickKey> {
     use Key::*;
-    vec![
+    [
         (D, 1, 2),
         (F, 1, 3),
         (S, 2, 1),
@@ -51,6 +143,82 @@ pub fn quick_keys() -> Vec<(Key, usize, usize)> {
         (J, 3, 1),
         (K, 3, 2),
     ]
+        .into_iter()
+        .map(|(key, from, to)| (key.into(), from, to))
+        .collect()
+}
+
+/// How evenly spaced the moves are, as a percentage: 100% means every move took exactly the same time.
+pub fn consistency_score(moves: &[Move]) -> Option<f64> {
+    let intervals = moves
+        .windows(2)
+        .map(|pair| (pair[1].0.saturating_sub(pair[0].0)).as_secs_f64())
+        .collect::<Vec<f64>>();
+    if intervals.len() < 2 { return None }
+
+    let count = intervals.len() as f64;
+    let mean = intervals.iter().sum::<f64>() / count;
+    if mean <= 0.0 { return None }
+    let variance = intervals.iter().map(|interval| (interval - mean).powi(2)).sum::<f64>() / count;
+
+    Some((1.0 - variance.sqrt() / mean).max(0.0) * 100.0)
+}
+
+/// The column pole `pole` is drawn in, and the other way around since mirroring is its own inverse.
+pub fn pole_column(pole: usize, poles_count: usize, mirror: bool) -> usize {
+    if mirror { poles_count - 1 - pole } else { pole }
+}
+
+/// The consistency above which a run is celebrated as metronomic.
+pub const PERFECT_CONSISTENCY: f64 = 95.0;
+
+pub fn is_perfectly_consistent(moves: &[Move]) -> bool {
+    consistency_score(moves).is_some_and(|consistency| consistency > PERFECT_CONSISTENCY)
+}
+
+/// How many moves each input method made, along with the average number of the disks it moved.
+/// `moves` are played back from the starting position of `game`'s settings.
+pub fn input_breakdown(game: &HanoiGame, moves: &[Move]) -> Vec<(InputSource, usize, f64)> {
+    let mut game = game.restarted();
+
+    let mut breakdown: Vec<(InputSource, usize, usize)> = vec![];
+    for &Move(_, from, to, input) in moves {
+        game.shift(from, to);
+        let (Some(input), Some(&disk_number)) = (input, game.poles[to].last()) else { continue };
+        match breakdown.iter_mut().find(|(source, _, _)| *source == input) {
+            Some((_, count, disks)) => {
+                *count += 1;
+                *disks += disk_number;
+            },
+            None => breakdown.push((input, 1, disk_number)),
+        }
+    }
+
+    breakdown
+        .into_iter()
+        .map(|(input, count, disks)| (input, count, disks as f64 / count as f64))
+        .collect()
+}
+
+/// The share of `moves` that brought the game one move closer to the end, along with the indices of the other ones.
+/// `None` when the optimal moves can't be computed for `game`'s settings.
+pub fn move_efficiency(game: &HanoiGame, moves: &[Move]) -> Option<(f64, Vec<usize>)> {
+    let mut game = game.restarted();
+    let mut remaining = game.optimal_remaining()?;
+    let mut wasteful = vec![];
+
+    for (i, &Move(_, from, to, _)) in moves.iter().enumerate() {
+        game.shift(from, to);
+        let next = game.optimal_remaining()?;
+        if next + 1 != remaining {
+            wasteful.push(i);
+        }
+        remaining = next;
+    }
+
+    if moves.is_empty() { return None }
+    let efficiency = (moves.len() - wasteful.len()) as f64 / moves.len() as f64 * 100.0;
+    Some((efficiency, wasteful))
 }
 
 #[inline]
@@ -61,3 +229,74 @@ pub fn get_cursor_position(ctx: &Context) -> Option<Pos2> {
         hover.or(interact)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{GameState, HanoiApp};
+
+    use super::{pole_column, ResetMode};
+
+    #[test]
+    fn pole_columns_in_both_orientations() {
+        for poles_count in 1..=16 {
+            for pole in 0..poles_count {
+                assert_eq!(pole_column(pole, poles_count, false), pole);
+                let column = pole_column(pole, poles_count, true);
+                assert_eq!(column, poles_count - 1 - pole);
+                assert_eq!(pole_column(column, poles_count, true), pole);
+            }
+        }
+        assert_eq!((0..3).map(|pole| pole_column(pole, 3, true)).collect::<Vec<_>>(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn scrambles_change_every_game_unless_the_seed_is_fixed() {
+        let mut app = HanoiApp { scramble: true, ..Default::default() };
+        app.soft_reset();
+        let seed = app.scramble_seed;
+        app.soft_reset();
+        assert_ne!(app.scramble_seed, seed, "a new game deals a new position");
+
+        let (seed, board) = (app.scramble_seed, app.hanoi.board());
+        app.reset_mode = ResetMode::BoardOnly;
+        app.retry();
+        assert_eq!((app.scramble_seed, app.hanoi.board()), (seed, board.clone()), "a retry deals the same one");
+
+        app.fixed_seed = true;
+        app.soft_reset();
+        assert_eq!((app.scramble_seed, app.hanoi.board()), (seed, board));
+    }
+
+    #[test]
+    fn reset_modes_keep_what_they_say() {
+        let mut app = HanoiApp::default();
+        app.hanoi.disks_count = 1;
+        app.soft_reset();
+        let header = app.get_current_header();
+
+        app.reset_mode = ResetMode::BoardOnly;
+        app.full_move(0, 2, None);
+        app.retry();
+        app.full_move(0, 2, None);
+        assert_eq!(app.attempts[&header], 1, "a board only reset isn't a new attempt");
+        assert_eq!(app.moves, 1);
+
+        app.reset_mode = ResetMode::Continuous;
+        app.retry();
+        let GameState::Playing(start) = app.state else { panic!("the clock stopped") };
+        assert_eq!((app.moves, app.hanoi.poles[0].len()), (0, 1));
+        app.retry();
+        assert_eq!(app.state, GameState::Playing(start), "the clock keeps running across resets");
+        app.full_move(0, 2, None);
+        app.check_finished(&Default::default());
+        assert!(matches!(app.state, GameState::Finished(_)));
+        assert!(!app.highscores.contains_key(&header), "a run across resets isn't saved");
+
+        app.retry();
+        assert_eq!(app.state, GameState::Reset, "a finished game starts over");
+        app.full_move(0, 2, None);
+        app.check_finished(&Default::default());
+        assert_eq!(app.highscores[&header].len(), 1);
+        assert_eq!(app.attempts[&header], 2);
+    }
+}